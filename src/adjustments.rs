@@ -0,0 +1,78 @@
+use image::{DynamicImage, Rgba};
+use serde::{Deserialize, Serialize};
+
+/// Filters applied to the pages of a book when they are decoded.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct ImageAdjustments {
+	/// Added to every color channel, from -100 to 100.
+	pub brightness: i32,
+	/// From -100 to 100, where 0 leaves the contrast as is.
+	pub contrast: f32,
+	/// Values below 1 darken the midtones and values above 1 lighten them.
+	pub gamma: f32,
+	/// The blur radius used to sharpen the page, or 0 to not sharpen it.
+	pub sharpen: f32,
+	pub grayscale: bool,
+	/// Inverts the colors, for reading at night.
+	pub invert: bool,
+}
+
+impl ImageAdjustments {
+	/// Whether applying the adjustments would leave the page as is.
+	pub fn is_identity(&self) -> bool {
+		*self == Self::default()
+	}
+
+	pub fn apply(&self, img: DynamicImage) -> DynamicImage {
+		if self.is_identity() {
+			return img;
+		}
+
+		let mut img = if self.grayscale { img.grayscale() } else { img };
+		if self.brightness != 0 {
+			img = img.brighten(self.brightness);
+		}
+		if self.contrast != 0.0 {
+			img = img.adjust_contrast(self.contrast);
+		}
+		if self.gamma != 1.0 {
+			img = apply_gamma(img, self.gamma);
+		}
+		if self.sharpen > 0.0 {
+			img = img.unsharpen(self.sharpen, 1);
+		}
+		if self.invert {
+			img.invert();
+		}
+		img
+	}
+}
+
+impl Default for ImageAdjustments {
+	fn default() -> Self {
+		Self {
+			brightness: 0,
+			contrast: 0.0,
+			gamma: 1.0,
+			sharpen: 0.0,
+			grayscale: false,
+			invert: false,
+		}
+	}
+}
+
+fn apply_gamma(img: DynamicImage, gamma: f32) -> DynamicImage {
+	let lut: [u8; 256] = std::array::from_fn(|v| {
+		let v = (v as f32 / 255.0).powf(1.0 / gamma);
+		(v * 255.0).round().clamp(0.0, 255.0) as u8
+	});
+
+	let mut img = img.into_rgba8();
+	for Rgba([r, g, b, _]) in img.pixels_mut() {
+		*r = lut[*r as usize];
+		*g = lut[*g as usize];
+		*b = lut[*b as usize];
+	}
+	DynamicImage::ImageRgba8(img)
+}
@@ -0,0 +1,50 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The current time in seconds since the Unix epoch.
+pub fn unix_now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |d| d.as_secs())
+}
+
+/// Turns days since the Unix epoch into a year, month and day, using the
+/// proleptic Gregorian calendar.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+	let z = days + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z.rem_euclid(146_097);
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+	let year = yoe + era * 400 + i64::from(month <= 2);
+	(year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn converts_days_to_dates() {
+		assert_eq!(civil_from_days(0), (1970, 1, 1));
+		assert_eq!(civil_from_days(-1), (1969, 12, 31));
+		assert_eq!(civil_from_days(31), (1970, 2, 1));
+		assert_eq!(civil_from_days(10_956), (1999, 12, 31));
+		assert_eq!(civil_from_days(10_957), (2000, 1, 1));
+		assert_eq!(civil_from_days(19_722), (2023, 12, 31));
+	}
+
+	#[test]
+	fn converts_leap_days_to_dates() {
+		assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+		assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+		assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+		// 1900 wasn't a leap year.
+		assert_eq!(civil_from_days(-25_509), (1900, 2, 28));
+		assert_eq!(civil_from_days(-25_508), (1900, 3, 1));
+	}
+}
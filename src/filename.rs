@@ -0,0 +1,386 @@
+/// What can be told about a book from its file name.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FileInfo {
+	pub series: Option<String>,
+	pub volume: Option<u32>,
+	/// A chapter number such as `45` or `150.5`, or a range such as `1-6`.
+	pub chapter: Option<String>,
+	pub year: Option<u16>,
+	/// The scanlation group that released the file.
+	pub group: Option<String>,
+}
+
+/// Parenthesized tags that describe the release rather than who made it.
+const RELEASE_TAGS: [&str; 16] = [
+	"digital",
+	"digital-hd",
+	"webrip",
+	"web",
+	"c2c",
+	"f",
+	"f1",
+	"f2",
+	"hq",
+	"lq",
+	"color",
+	"colour",
+	"colored",
+	"english",
+	"eng",
+	"omnibus",
+];
+
+/// Parses the common manga naming conventions, such as
+/// `One Piece v012 (2004) (Digital)` and `[Group] Title - c045`, from a file
+/// name without its extension.
+pub fn parse_filename(name: &str) -> FileInfo {
+	let mut info = FileInfo::default();
+	let (rest, brackets, parens) = split_groups(name);
+
+	info.group = brackets.first().cloned();
+	for group in parens.iter().rev() {
+		match parse_year(group) {
+			Some(year) if info.year.is_none() => info.year = Some(year),
+			Some(_) => {}
+			None if info.group.is_none()
+				&& !RELEASE_TAGS.contains(&group.to_lowercase().as_str()) =>
+			{
+				info.group = Some(group.clone());
+			}
+			None => {}
+		}
+	}
+
+	// Some files use underscores or dots instead of spaces.
+	let rest = rest.replace('_', " ");
+	let rest = if rest.contains(' ') {
+		rest
+	} else {
+		rest.replace('.', " ")
+	};
+	let words = rest.split_whitespace().collect::<Vec<&str>>();
+
+	let mut series_end = words.len();
+	let mut index = 0;
+	while index < words.len() {
+		let (marker, consumed) = parse_marker(&words[index..]);
+		match marker {
+			Some(Marker::Volume(volume)) if info.volume.is_none() => {
+				info.volume = Some(volume);
+			}
+			Some(Marker::Chapter(chapter)) if info.chapter.is_none() => {
+				info.chapter = Some(chapter);
+			}
+			_ => {
+				index += 1;
+				continue;
+			}
+		}
+		series_end = series_end.min(index);
+		index += consumed;
+	}
+
+	// A number on its own at the end is a chapter after a dash, as in
+	// `Blame! - 012`, and a volume otherwise, as in `Akira 01`.
+	if info.volume.is_none() && info.chapter.is_none() && series_end > 1 {
+		if let Some(number) = parse_number(words[series_end - 1]) {
+			series_end -= 1;
+			if words[series_end - 1] == "-" {
+				info.chapter = Some(number);
+			} else {
+				info.volume = number.parse().ok();
+			}
+		}
+	}
+
+	let series = words[..series_end].join(" ");
+	let series = series.trim_end_matches(['-', ' ']).trim();
+	if !series.is_empty() {
+		info.series = Some(series.to_owned());
+	}
+	info
+}
+
+enum Marker {
+	Volume(u32),
+	Chapter(String),
+}
+
+/// Finds a volume or chapter marker at the start of the words, along with
+/// the number of words it takes up.
+fn parse_marker(words: &[&str]) -> (Option<Marker>, usize) {
+	let word = words[0].to_lowercase();
+	let next = words.get(1).copied();
+
+	const VOLUME: [&str; 4] = ["volume", "vol.", "vol", "v"];
+	const CHAPTER: [&str; 6] = ["chapter", "ch.", "ch", "c", "#", "episode"];
+	for (prefixes, is_volume) in [(&VOLUME[..], true), (&CHAPTER[..], false)] {
+		for prefix in prefixes {
+			let Some(number) = word.strip_prefix(prefix) else {
+				continue;
+			};
+			let (number, consumed) = match (number.is_empty(), next) {
+				// The number is the next word, as in `Vol. 3`.
+				(true, Some(next)) => (parse_number(next), 2),
+				(true, None) => (None, 1),
+				(false, _) => (parse_number(number), 1),
+			};
+			let Some(number) = number else {
+				continue;
+			};
+			let marker = if is_volume {
+				match number.parse() {
+					Ok(volume) => Marker::Volume(volume),
+					Err(_) => continue,
+				}
+			} else {
+				Marker::Chapter(number)
+			};
+			return (Some(marker), consumed);
+		}
+	}
+	(None, 1)
+}
+
+/// Parses a number such as `012`, `150.5` or `001-006` without its leading
+/// zeros.
+fn parse_number(word: &str) -> Option<String> {
+	let parts = word
+		.split('-')
+		.map(|part| {
+			let (whole, fraction) = match part.split_once('.') {
+				Some((whole, fraction)) => (whole, Some(fraction)),
+				None => (part, None),
+			};
+			let is_digits = |s: &str| {
+				!s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+			};
+			if !is_digits(whole) || !fraction.is_none_or(is_digits) {
+				return None;
+			}
+			let whole = whole.trim_start_matches('0');
+			let whole = if whole.is_empty() { "0" } else { whole };
+			Some(match fraction {
+				Some(fraction) => format!("{whole}.{fraction}"),
+				None => whole.to_owned(),
+			})
+		})
+		.collect::<Option<Vec<String>>>()?;
+	(parts.len() <= 2).then(|| parts.join("-"))
+}
+
+fn parse_year(group: &str) -> Option<u16> {
+	let year = group.parse::<u16>().ok()?;
+	(1900..2100).contains(&year).then_some(year)
+}
+
+/// Takes the `[...]` and `(...)` groups out of the name, returning the rest
+/// of it and the contents of the groups in order.
+fn split_groups(name: &str) -> (String, Vec<String>, Vec<String>) {
+	let mut rest = String::new();
+	let mut brackets = Vec::new();
+	let mut parens = Vec::new();
+	let mut group: Option<(char, String)> = None;
+	for c in name.chars() {
+		match (&mut group, c) {
+			(None, '[') => group = Some((']', String::new())),
+			(None, '(') => group = Some((')', String::new())),
+			(None, c) => rest.push(c),
+			(Some((close, contents)), c) if c == *close => {
+				let contents = contents.trim().to_owned();
+				if !contents.is_empty() {
+					if c == ']' {
+						brackets.push(contents);
+					} else {
+						parens.push(contents);
+					}
+				}
+				group = None;
+				rest.push(' ');
+			}
+			(Some((_, contents)), c) => contents.push(c),
+		}
+	}
+	// An unclosed group is part of the name after all.
+	if let Some((close, contents)) = group {
+		rest.push(if close == ']' { '[' } else { '(' });
+		rest.push_str(&contents);
+	}
+	(rest, brackets, parens)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Case {
+		name: &'static str,
+		series: Option<&'static str>,
+		volume: Option<u32>,
+		chapter: Option<&'static str>,
+		year: Option<u16>,
+		group: Option<&'static str>,
+	}
+
+	const CASES: &[Case] = &[
+		Case {
+			name: "One Piece v012 (2004) (Digital)",
+			series: Some("One Piece"),
+			volume: Some(12),
+			chapter: None,
+			year: Some(2004),
+			group: None,
+		},
+		Case {
+			name: "[Group] Title - c045",
+			series: Some("Title"),
+			volume: None,
+			chapter: Some("45"),
+			year: None,
+			group: Some("Group"),
+		},
+		Case {
+			name: "Berserk v01 (2003) (Digital) (danke-Empire)",
+			series: Some("Berserk"),
+			volume: Some(1),
+			chapter: None,
+			year: Some(2003),
+			group: Some("danke-Empire"),
+		},
+		Case {
+			name: "Vinland Saga Vol. 3",
+			series: Some("Vinland Saga"),
+			volume: Some(3),
+			chapter: None,
+			year: None,
+			group: None,
+		},
+		Case {
+			name: "Vinland_Saga_v03",
+			series: Some("Vinland Saga"),
+			volume: Some(3),
+			chapter: None,
+			year: None,
+			group: None,
+		},
+		Case {
+			name: "Attack on Titan - Chapter 139",
+			series: Some("Attack on Titan"),
+			volume: None,
+			chapter: Some("139"),
+			year: None,
+			group: None,
+		},
+		Case {
+			name: "[HorribleScans] Kaguya-sama - Love Is War - ch.150.5",
+			series: Some("Kaguya-sama - Love Is War"),
+			volume: None,
+			chapter: Some("150.5"),
+			year: None,
+			group: Some("HorribleScans"),
+		},
+		Case {
+			name: "Dorohedoro v01 c001-006",
+			series: Some("Dorohedoro"),
+			volume: Some(1),
+			chapter: Some("1-6"),
+			year: None,
+			group: None,
+		},
+		Case {
+			name: "Akira 01",
+			series: Some("Akira"),
+			volume: Some(1),
+			chapter: None,
+			year: None,
+			group: None,
+		},
+		Case {
+			name: "Blame! - 012",
+			series: Some("Blame!"),
+			volume: None,
+			chapter: Some("12"),
+			year: None,
+			group: None,
+		},
+		Case {
+			name: "Oyasumi Punpun Volume 5 (2008)",
+			series: Some("Oyasumi Punpun"),
+			volume: Some(5),
+			chapter: None,
+			year: Some(2008),
+			group: None,
+		},
+		Case {
+			name: "20th Century Boys v01 (F)",
+			series: Some("20th Century Boys"),
+			volume: Some(1),
+			chapter: None,
+			year: None,
+			group: None,
+		},
+		Case {
+			name: "Chainsaw.Man.v02.c010",
+			series: Some("Chainsaw Man"),
+			volume: Some(2),
+			chapter: Some("10"),
+			year: None,
+			group: None,
+		},
+		Case {
+			name: "Monster",
+			series: Some("Monster"),
+			volume: None,
+			chapter: None,
+			year: None,
+			group: None,
+		},
+		Case {
+			name: "Yotsuba&! v15 (2022) (Digital) (1r0n)",
+			series: Some("Yotsuba&!"),
+			volume: Some(15),
+			chapter: None,
+			year: Some(2022),
+			group: Some("1r0n"),
+		},
+		Case {
+			name: "[Scans] Vagabond #300",
+			series: Some("Vagabond"),
+			volume: None,
+			chapter: Some("300"),
+			year: None,
+			group: Some("Scans"),
+		},
+		Case {
+			name: "Solo Leveling (2018",
+			series: Some("Solo Leveling (2018"),
+			volume: None,
+			chapter: None,
+			year: None,
+			group: None,
+		},
+	];
+
+	#[test]
+	fn parses_real_world_names() {
+		for case in CASES {
+			let expected = FileInfo {
+				series: case.series.map(str::to_owned),
+				volume: case.volume,
+				chapter: case.chapter.map(str::to_owned),
+				year: case.year,
+				group: case.group.map(str::to_owned),
+			};
+			assert_eq!(parse_filename(case.name), expected, "{}", case.name);
+		}
+	}
+
+	#[test]
+	fn strips_leading_zeros() {
+		assert_eq!(parse_number("007").as_deref(), Some("7"));
+		assert_eq!(parse_number("000").as_deref(), Some("0"));
+		assert_eq!(parse_number("010.50").as_deref(), Some("10.50"));
+		assert_eq!(parse_number("1-2-3"), None);
+		assert_eq!(parse_number("12a"), None);
+	}
+}
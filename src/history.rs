@@ -0,0 +1,362 @@
+use crate::dates::{civil_from_days, unix_now, SECONDS_PER_DAY};
+use crate::library::Library;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	path::{Path, PathBuf},
+	time::Duration,
+};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+/// The longest time between two page turns that still counts as reading.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// The number of days listed in the statistics.
+const RECENT_DAYS: i64 = 14;
+/// The number of authors and tags listed in the statistics.
+const TOP_COUNT: usize = 5;
+
+/// A page turned in the viewer.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct PageTurn {
+	pub book: Uuid,
+	/// The page that was turned to.
+	pub page: usize,
+	pub page_count: usize,
+	/// Seconds since the Unix epoch.
+	pub timestamp: u64,
+}
+
+impl PageTurn {
+	pub fn now(book: Uuid, page: usize, page_count: usize) -> Self {
+		Self {
+			book,
+			page,
+			page_count,
+			timestamp: unix_now(),
+		}
+	}
+
+	/// The day the page was turned on, counted from the Unix epoch in UTC.
+	fn day(&self) -> i64 {
+		(self.timestamp / SECONDS_PER_DAY) as i64
+	}
+}
+
+/// The current day, counted from the Unix epoch in UTC.
+pub fn today() -> i64 {
+	(unix_now() / SECONDS_PER_DAY) as i64
+}
+
+/// The page turns of every reading session, in the order they happened.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+	events: Vec<PageTurn>,
+}
+
+/// The history file is kept next to the library file, so that each library
+/// has its own.
+pub fn history_path(library_file: &Path) -> PathBuf {
+	library_file.with_extension("history.jsonl")
+}
+
+impl History {
+	pub async fn load(path: PathBuf) -> Result<Self, String> {
+		let Ok(b) = tokio::fs::read_to_string(&path).await else {
+			return Ok(Self::default());
+		};
+		let events = b
+			.lines()
+			.filter(|line| !line.trim().is_empty())
+			.map(serde_json::from_str)
+			.collect::<Result<Vec<PageTurn>, _>>()
+			.map_err(|err| {
+				let msg = "Unable to parse history file";
+				eprintln!("{msg}: {err}");
+				msg.to_owned()
+			})?;
+		Ok(Self { events })
+	}
+
+	pub fn push(&mut self, event: PageTurn) {
+		self.events.push(event);
+	}
+
+	/// Sums up the history, looking the books up in the library for their
+	/// authors and tags.
+	pub fn stats(&self, library: &Library) -> ReadingStats {
+		let today = today();
+		let mut days: BTreeMap<i64, DayStats> = BTreeMap::new();
+		let mut books_per_day: HashMap<i64, HashSet<Uuid>> = HashMap::new();
+		let mut finished = HashSet::new();
+		let mut pages_per_book: HashMap<Uuid, usize> = HashMap::new();
+
+		let mut previous: Option<&PageTurn> = None;
+		for event in &self.events {
+			let day = days.entry(event.day()).or_insert(DayStats {
+				day: event.day(),
+				..DayStats::default()
+			});
+			day.pages += 1;
+			if let Some(previous) = previous {
+				let gap = Duration::from_secs(
+					event.timestamp.saturating_sub(previous.timestamp),
+				);
+				if gap <= IDLE_TIMEOUT {
+					day.time += gap;
+				}
+			}
+			books_per_day
+				.entry(event.day())
+				.or_default()
+				.insert(event.book);
+			if event.page + 1 >= event.page_count {
+				finished.insert(event.book);
+			}
+			*pages_per_book.entry(event.book).or_default() += 1;
+			previous = Some(event);
+		}
+		for (day, books) in books_per_day {
+			if let Some(stats) = days.get_mut(&day) {
+				stats.books = books.len();
+			}
+		}
+
+		let mut pages_per_author: HashMap<String, usize> = HashMap::new();
+		let mut pages_per_tag: HashMap<String, usize> = HashMap::new();
+		for (id, pages) in pages_per_book {
+			let Some(book) = library.get_book(&id) else {
+				continue;
+			};
+			let book = book.read().unwrap();
+			if !book.get_author().is_empty() {
+				*pages_per_author
+					.entry(book.get_author().to_owned())
+					.or_default() += pages;
+			}
+			for tag in book.get_tags() {
+				*pages_per_tag.entry(tag.clone()).or_default() += pages;
+			}
+		}
+
+		let (current_streak, longest_streak) =
+			streaks(days.keys().copied(), today);
+		ReadingStats {
+			total_pages: self.events.len(),
+			total_time: days.values().map(|d| d.time).sum(),
+			books_finished: finished.len(),
+			current_streak,
+			longest_streak,
+			recent_days: days
+				.range(today - RECENT_DAYS + 1..)
+				.rev()
+				.map(|(_, stats)| *stats)
+				.collect(),
+			top_authors: top(pages_per_author),
+			top_tags: top(pages_per_tag),
+		}
+	}
+}
+
+/// Appends the page turn to the history file.
+pub async fn log_page_turn(
+	path: PathBuf,
+	event: PageTurn,
+) -> Result<(), String> {
+	let mut line = serde_json::to_string(&event).map_err(|err| {
+		let msg = "Unable to serialize page turn";
+		eprintln!("{msg}: {err}");
+		msg.to_owned()
+	})?;
+	line.push('\n');
+	let write = async {
+		let mut file = tokio::fs::OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&path)
+			.await?;
+		file.write_all(line.as_bytes()).await?;
+		file.flush().await
+	};
+	write.await.map_err(|err| {
+		let msg = "Unable to write history file";
+		eprintln!("{msg}: {err}");
+		msg.to_owned()
+	})
+}
+
+/// The reading done on a single day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DayStats {
+	/// Counted from the Unix epoch in UTC.
+	pub day: i64,
+	/// The number of different books read.
+	pub books: usize,
+	pub pages: usize,
+	pub time: Duration,
+}
+
+impl DayStats {
+	/// The day as a `YYYY-MM-DD` date.
+	pub fn date(&self) -> String {
+		let (year, month, day) = civil_from_days(self.day);
+		format!("{year:04}-{month:02}-{day:02}")
+	}
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadingStats {
+	pub total_pages: usize,
+	pub total_time: Duration,
+	/// The number of books read up to their last page.
+	pub books_finished: usize,
+	/// The consecutive days of reading up to today, or up to yesterday if
+	/// nothing was read yet today.
+	pub current_streak: usize,
+	pub longest_streak: usize,
+	/// The days with reading in the last two weeks, newest first.
+	pub recent_days: Vec<DayStats>,
+	/// The most read authors with their number of pages read.
+	pub top_authors: Vec<(String, usize)>,
+	pub top_tags: Vec<(String, usize)>,
+}
+
+/// Finds the current and longest runs of consecutive days in the sorted days.
+fn streaks(days: impl Iterator<Item = i64>, today: i64) -> (usize, usize) {
+	let mut longest = 0;
+	let mut run = 0;
+	let mut last = None;
+	for day in days {
+		run = match last {
+			Some(last) if day == last + 1 => run + 1,
+			_ => 1,
+		};
+		longest = longest.max(run);
+		last = Some(day);
+	}
+	let current = match last {
+		Some(last) if last >= today - 1 => run,
+		_ => 0,
+	};
+	(current, longest)
+}
+
+fn top(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+	let mut counts = counts.into_iter().collect::<Vec<_>>();
+	counts
+		.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then(a_name.cmp(b_name)));
+	counts.truncate(TOP_COUNT);
+	counts
+}
+
+/// Formats a reading time as hours and minutes, such as `1h 05m`.
+pub fn format_duration(time: Duration) -> String {
+	let minutes = time.as_secs() / 60;
+	if minutes < 60 {
+		format!("{minutes}m")
+	} else {
+		format!("{}h {:02}m", minutes / 60, minutes % 60)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn turn(book: Uuid, page: usize, timestamp: u64) -> PageTurn {
+		PageTurn {
+			book,
+			page,
+			page_count: 10,
+			timestamp,
+		}
+	}
+
+	/// The start of today, so that the tests don't depend on the time of
+	/// day they run at.
+	fn start_of_today() -> u64 {
+		today() as u64 * SECONDS_PER_DAY
+	}
+
+	#[test]
+	fn formats_day_stats_dates() {
+		let stats = DayStats {
+			day: 19_782,
+			..DayStats::default()
+		};
+		assert_eq!(stats.date(), "2024-02-29");
+	}
+
+	#[test]
+	fn finds_current_and_longest_streaks() {
+		assert_eq!(streaks([].into_iter(), 100), (0, 0));
+		assert_eq!(streaks([90, 91, 92, 98, 99, 100].into_iter(), 100), (3, 3));
+		assert_eq!(streaks([90, 91, 92, 93, 99].into_iter(), 100), (1, 4));
+	}
+
+	#[test]
+	fn keeps_streak_until_today_is_over() {
+		// Nothing read yet today still continues yesterday's streak.
+		assert_eq!(streaks([97, 98, 99].into_iter(), 100), (3, 3));
+		assert_eq!(streaks([96, 97, 98].into_iter(), 100), (0, 3));
+	}
+
+	#[test]
+	fn counts_reading_time_up_to_the_idle_timeout() {
+		let book = Uuid::new_v4();
+		let start = start_of_today();
+		let history = History {
+			events: vec![
+				turn(book, 1, start),
+				turn(book, 2, start + 60),
+				// Exactly the timeout still counts as reading.
+				turn(book, 3, start + 60 + 300),
+				// Longer means the reader was away.
+				turn(book, 4, start + 60 + 300 + 301),
+			],
+		};
+		let stats = history.stats(&Library::default());
+		assert_eq!(stats.total_pages, 4);
+		assert_eq!(stats.total_time, Duration::from_secs(360));
+	}
+
+	#[test]
+	fn sums_up_days_books_authors_and_tags() {
+		let mut library = Library::default();
+		let one_piece = library.add_book(Path::new("/manga/One Piece v01.cbz"));
+		one_piece
+			.write()
+			.unwrap()
+			.set_author("Eiichiro Oda".to_owned());
+		one_piece
+			.write()
+			.unwrap()
+			.set_tags(vec!["Shonen".to_owned()]);
+		let (one_piece, other) =
+			(one_piece.read().unwrap().get_id(), Uuid::new_v4());
+		let today = start_of_today();
+		let yesterday = today - SECONDS_PER_DAY;
+		let history = History {
+			events: vec![
+				turn(one_piece, 8, yesterday),
+				turn(one_piece, 9, yesterday + 10),
+				turn(one_piece, 1, today),
+				turn(other, 1, today + 10),
+			],
+		};
+
+		let stats = history.stats(&library);
+		assert_eq!(stats.books_finished, 1);
+		assert_eq!(stats.current_streak, 2);
+		assert_eq!(stats.longest_streak, 2);
+		let days = stats
+			.recent_days
+			.iter()
+			.map(|d| (d.day, d.books, d.pages))
+			.collect::<Vec<_>>();
+		let today = (today / SECONDS_PER_DAY) as i64;
+		assert_eq!(days, [(today, 2, 2), (today - 1, 1, 2)]);
+		assert_eq!(stats.top_authors, [("Eiichiro Oda".to_owned(), 3)]);
+		assert_eq!(stats.top_tags, [("Shonen".to_owned(), 3)]);
+	}
+}
@@ -0,0 +1,633 @@
+use iced::keyboard::{KeyCode, Modifiers};
+use iced::mouse;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, path::PathBuf, str::FromStr};
+
+/// Something the user can trigger with a key binding.
+#[derive(
+	Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum Action {
+	NextPage,
+	PreviousPage,
+	FirstPage,
+	LastPage,
+	SkipForward,
+	SkipBackward,
+	CycleFitMode,
+	FitBest,
+	FitWidth,
+	FitHeight,
+	FitOriginal,
+	FitFill,
+	ToggleBookmark,
+	ToggleFilmstrip,
+	ToggleOverview,
+	ToggleFullscreen,
+	ToggleAutoCrop,
+	ToggleAdjustments,
+	RotatePage,
+	RotateBook,
+	ToggleSlideshow,
+	ToggleSplitSpreads,
+	ZoomIn,
+	ZoomOut,
+	ResetZoom,
+	Back,
+}
+
+impl Action {
+	pub const ALL: [Action; 26] = [
+		Action::NextPage,
+		Action::PreviousPage,
+		Action::FirstPage,
+		Action::LastPage,
+		Action::SkipForward,
+		Action::SkipBackward,
+		Action::CycleFitMode,
+		Action::FitBest,
+		Action::FitWidth,
+		Action::FitHeight,
+		Action::FitOriginal,
+		Action::FitFill,
+		Action::ToggleBookmark,
+		Action::ToggleFilmstrip,
+		Action::ToggleOverview,
+		Action::ToggleFullscreen,
+		Action::ToggleAutoCrop,
+		Action::ToggleAdjustments,
+		Action::RotatePage,
+		Action::RotateBook,
+		Action::ToggleSlideshow,
+		Action::ToggleSplitSpreads,
+		Action::ZoomIn,
+		Action::ZoomOut,
+		Action::ResetZoom,
+		Action::Back,
+	];
+
+	fn default_bindings(self) -> &'static [&'static str] {
+		match self {
+			Action::NextPage => {
+				&["Right", "L", "J", "Space", "RightZone", "MouseForward"]
+			}
+			Action::PreviousPage => {
+				&["Left", "H", "K", "Shift+Space", "LeftZone", "MouseBack"]
+			}
+			Action::FirstPage => &["Home", "G"],
+			Action::LastPage => &["End", "Shift+G"],
+			Action::SkipForward => &["PageDown", "Ctrl+F"],
+			Action::SkipBackward => &["PageUp", "Ctrl+B"],
+			Action::CycleFitMode => &["M"],
+			Action::FitBest => &["Key1"],
+			Action::FitWidth => &["Key2"],
+			Action::FitHeight => &["Key3"],
+			Action::FitOriginal => &["Key4"],
+			Action::FitFill => &["Key5"],
+			Action::ToggleBookmark => &["B"],
+			Action::ToggleFilmstrip => &["T"],
+			Action::ToggleOverview => &["O"],
+			Action::ToggleFullscreen => &["F11", "F", "MouseMiddle"],
+			Action::ToggleAutoCrop => &["C"],
+			Action::ToggleAdjustments => &["A"],
+			Action::RotatePage => &["R"],
+			Action::RotateBook => &["Shift+R"],
+			Action::ToggleSlideshow => &["S"],
+			Action::ToggleSplitSpreads => &["D"],
+			Action::ZoomIn => &["Equals", "Plus", "NumpadAdd"],
+			Action::ZoomOut => &["Minus", "NumpadSubtract"],
+			Action::ResetZoom => &["Key0", "Numpad0"],
+			Action::Back => &["Escape", "Q"],
+		}
+	}
+}
+
+impl fmt::Display for Action {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Action::NextPage => "Next page",
+			Action::PreviousPage => "Previous page",
+			Action::FirstPage => "First page",
+			Action::LastPage => "Last page",
+			Action::SkipForward => "Skip forward",
+			Action::SkipBackward => "Skip backward",
+			Action::CycleFitMode => "Cycle fit mode",
+			Action::FitBest => "Best fit",
+			Action::FitWidth => "Fit width",
+			Action::FitHeight => "Fit height",
+			Action::FitOriginal => "Original size",
+			Action::FitFill => "Fill",
+			Action::ToggleBookmark => "Toggle bookmark",
+			Action::ToggleFilmstrip => "Toggle filmstrip",
+			Action::ToggleOverview => "Toggle page overview",
+			Action::ToggleFullscreen => "Toggle fullscreen",
+			Action::ToggleAutoCrop => "Toggle auto-crop",
+			Action::ToggleAdjustments => "Toggle image adjustments",
+			Action::RotatePage => "Rotate page",
+			Action::RotateBook => "Rotate all pages",
+			Action::ToggleSlideshow => "Start or stop slideshow",
+			Action::ToggleSplitSpreads => "Split double pages",
+			Action::ZoomIn => "Zoom in",
+			Action::ZoomOut => "Zoom out",
+			Action::ResetZoom => "Reset zoom",
+			Action::Back => "Back",
+		})
+	}
+}
+
+/// A mouse input that can be bound to an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseInput {
+	/// A click on the left half of the page.
+	LeftZone,
+	/// A click on the right half of the page.
+	RightZone,
+	Middle,
+	Back,
+	Forward,
+}
+
+impl MouseInput {
+	pub const ZONES: [MouseInput; 2] =
+		[MouseInput::LeftZone, MouseInput::RightZone];
+
+	/// The input for a mouse button, other than the left button which is
+	/// bound through the click zones.
+	pub fn from_button(button: mouse::Button) -> Option<Self> {
+		match button {
+			mouse::Button::Middle => Some(MouseInput::Middle),
+			mouse::Button::Other(other) if BACK_BUTTONS.contains(&other) => {
+				Some(MouseInput::Back)
+			}
+			mouse::Button::Other(other) if FORWARD_BUTTONS.contains(&other) => {
+				Some(MouseInput::Forward)
+			}
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for MouseInput {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			MouseInput::LeftZone => "LeftZone",
+			MouseInput::RightZone => "RightZone",
+			MouseInput::Middle => "MouseMiddle",
+			MouseInput::Back => "MouseBack",
+			MouseInput::Forward => "MouseForward",
+		})
+	}
+}
+
+/// The button numbers of the extra mouse buttons differ per platform.
+const BACK_BUTTONS: &[u16] = if cfg!(windows) {
+	&[1]
+} else if cfg!(target_os = "macos") {
+	&[3]
+} else {
+	&[8, 0x113]
+};
+const FORWARD_BUTTONS: &[u16] = if cfg!(windows) {
+	&[2]
+} else if cfg!(target_os = "macos") {
+	&[4]
+} else {
+	&[9, 0x114]
+};
+
+/// A key or mouse input that can trigger an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+	Key(KeyCode),
+	Mouse(MouseInput),
+}
+
+impl fmt::Display for Input {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Input::Key(key_code) => f.write_str(key_name(*key_code)),
+			Input::Mouse(mouse_input) => write!(f, "{mouse_input}"),
+		}
+	}
+}
+
+/// An input together with the modifiers that have to be held down with it.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyBinding {
+	input: Input,
+	modifiers: Modifiers,
+}
+
+impl KeyBinding {
+	pub fn new(input: Input, modifiers: Modifiers) -> Self {
+		Self { input, modifiers }
+	}
+
+	fn matches(&self, input: Input, modifiers: Modifiers) -> bool {
+		self.input == input && self.modifiers == modifiers
+	}
+}
+
+impl fmt::Display for KeyBinding {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (modifier, name) in MODIFIER_NAMES {
+			if self.modifiers.contains(modifier) {
+				write!(f, "{name}+")?;
+			}
+		}
+		write!(f, "{}", self.input)
+	}
+}
+
+impl FromStr for KeyBinding {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut parts = s.split('+').map(str::trim).collect::<Vec<&str>>();
+		let key = parts
+			.pop()
+			.filter(|k| !k.is_empty())
+			.ok_or_else(|| format!("Key binding \"{s}\" is missing a key"))?;
+		let input = KEY_NAMES
+			.iter()
+			.find(|(_, name)| name.eq_ignore_ascii_case(key))
+			.map(|(key_code, _)| Input::Key(*key_code))
+			.or_else(|| {
+				MOUSE_INPUTS
+					.into_iter()
+					.find(|m| m.to_string().eq_ignore_ascii_case(key))
+					.map(Input::Mouse)
+			})
+			.ok_or_else(|| format!("Unknown key \"{key}\""))?;
+
+		let mut modifiers = Modifiers::empty();
+		for part in parts {
+			let modifier = MODIFIER_NAMES
+				.iter()
+				.find(|(_, name)| name.eq_ignore_ascii_case(part))
+				.map(|(modifier, _)| *modifier)
+				.ok_or_else(|| format!("Unknown modifier \"{part}\""))?;
+			modifiers |= modifier;
+		}
+
+		Ok(Self::new(input, modifiers))
+	}
+}
+
+impl TryFrom<String> for KeyBinding {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<KeyBinding> for String {
+	fn from(value: KeyBinding) -> Self {
+		value.to_string()
+	}
+}
+
+/// The key bindings for every [`Action`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Keymap {
+	bindings: BTreeMap<Action, Vec<KeyBinding>>,
+}
+
+impl Keymap {
+	/// Loads the keymap file, creating one with the defaults if missing.
+	pub async fn load(path: PathBuf) -> Result<Self, String> {
+		if let Ok(b) = tokio::fs::read(&path).await {
+			let mut keymap: Self =
+				serde_json::from_slice(&b).map_err(|err| {
+					let msg = "Unable to parse keymap file";
+					eprintln!("{msg}: {err}");
+					msg.to_owned()
+				})?;
+			// Actions added since the file was written get their defaults.
+			for (action, bindings) in Self::default().bindings {
+				keymap.bindings.entry(action).or_insert(bindings);
+			}
+			return Ok(keymap);
+		}
+
+		let keymap = Self::default();
+		keymap.clone().save(path).await?;
+		Ok(keymap)
+	}
+
+	pub async fn save(self, path: PathBuf) -> Result<(), String> {
+		let json = serde_json::to_vec_pretty(&self).map_err(|err| {
+			let msg = "Unable to serialize keymap";
+			eprintln!("{msg}: {err}");
+			msg.to_owned()
+		})?;
+		tokio::fs::write(&path, &json).await.map_err(|err| {
+			let msg = "Unable to save keymap file";
+			eprintln!("{msg}: {err}");
+			msg.to_owned()
+		})?;
+		Ok(())
+	}
+
+	pub fn get_bindings(&self, action: Action) -> &[KeyBinding] {
+		self.bindings.get(&action).map_or(&[], |b| b.as_slice())
+	}
+
+	/// Adds the binding to the action, removing it from any other action.
+	pub fn add_binding(&mut self, action: Action, binding: KeyBinding) {
+		for bindings in self.bindings.values_mut() {
+			bindings.retain(|b| *b != binding);
+		}
+		self.bindings.entry(action).or_default().push(binding);
+	}
+
+	pub fn remove_binding(&mut self, action: Action, binding: KeyBinding) {
+		if let Some(bindings) = self.bindings.get_mut(&action) {
+			bindings.retain(|b| *b != binding);
+		}
+	}
+
+	pub fn action_for(
+		&self,
+		input: Input,
+		modifiers: Modifiers,
+	) -> Option<Action> {
+		self.bindings.iter().find_map(|(action, bindings)| {
+			bindings
+				.iter()
+				.any(|b| b.matches(input, modifiers))
+				.then_some(*action)
+		})
+	}
+}
+
+impl Default for Keymap {
+	fn default() -> Self {
+		let bindings = Action::ALL
+			.iter()
+			.map(|action| {
+				let bindings = action
+					.default_bindings()
+					.iter()
+					.map(|b| b.parse().expect("Default bindings should parse"))
+					.collect();
+				(*action, bindings)
+			})
+			.collect();
+		Self { bindings }
+	}
+}
+
+const MOUSE_INPUTS: [MouseInput; 5] = [
+	MouseInput::LeftZone,
+	MouseInput::RightZone,
+	MouseInput::Middle,
+	MouseInput::Back,
+	MouseInput::Forward,
+];
+
+const MODIFIER_NAMES: [(Modifiers, &str); 4] = [
+	(Modifiers::CTRL, "Ctrl"),
+	(Modifiers::ALT, "Alt"),
+	(Modifiers::LOGO, "Logo"),
+	(Modifiers::SHIFT, "Shift"),
+];
+
+macro_rules! key_names {
+	($($key:ident),* $(,)?) => {
+		/// The keys that can be used in a binding, by name.
+		const KEY_NAMES: &[(KeyCode, &str)] =
+			&[$((KeyCode::$key, stringify!($key))),*];
+
+		/// The name a key is saved with in the keymap file.
+		///
+		/// The match is exhaustive, so that any key that can be captured
+		/// also has a name that parses back.
+		fn key_name(key_code: KeyCode) -> &'static str {
+			match key_code {
+				$(KeyCode::$key => stringify!($key)),*
+			}
+		}
+	};
+}
+
+key_names!(
+	Key1,
+	Key2,
+	Key3,
+	Key4,
+	Key5,
+	Key6,
+	Key7,
+	Key8,
+	Key9,
+	Key0,
+	A,
+	B,
+	C,
+	D,
+	E,
+	F,
+	G,
+	H,
+	I,
+	J,
+	K,
+	L,
+	M,
+	N,
+	O,
+	P,
+	Q,
+	R,
+	S,
+	T,
+	U,
+	V,
+	W,
+	X,
+	Y,
+	Z,
+	Escape,
+	F1,
+	F2,
+	F3,
+	F4,
+	F5,
+	F6,
+	F7,
+	F8,
+	F9,
+	F10,
+	F11,
+	F12,
+	F13,
+	F14,
+	F15,
+	F16,
+	F17,
+	F18,
+	F19,
+	F20,
+	F21,
+	F22,
+	F23,
+	F24,
+	Snapshot,
+	Scroll,
+	Pause,
+	Insert,
+	Home,
+	Delete,
+	End,
+	PageDown,
+	PageUp,
+	Left,
+	Up,
+	Right,
+	Down,
+	Backspace,
+	Enter,
+	Space,
+	Compose,
+	Caret,
+	Numlock,
+	Numpad0,
+	Numpad1,
+	Numpad2,
+	Numpad3,
+	Numpad4,
+	Numpad5,
+	Numpad6,
+	Numpad7,
+	Numpad8,
+	Numpad9,
+	NumpadAdd,
+	NumpadDivide,
+	NumpadDecimal,
+	NumpadComma,
+	NumpadEnter,
+	NumpadEquals,
+	NumpadMultiply,
+	NumpadSubtract,
+	AbntC1,
+	AbntC2,
+	Apostrophe,
+	Apps,
+	Asterisk,
+	At,
+	Ax,
+	Backslash,
+	Calculator,
+	Capital,
+	Colon,
+	Comma,
+	Convert,
+	Equals,
+	Grave,
+	Kana,
+	Kanji,
+	LAlt,
+	LBracket,
+	LControl,
+	LShift,
+	LWin,
+	Mail,
+	MediaSelect,
+	MediaStop,
+	Minus,
+	Mute,
+	MyComputer,
+	NavigateForward,
+	NavigateBackward,
+	NextTrack,
+	NoConvert,
+	OEM102,
+	Period,
+	PlayPause,
+	Plus,
+	Power,
+	PrevTrack,
+	RAlt,
+	RBracket,
+	RControl,
+	RShift,
+	RWin,
+	Semicolon,
+	Slash,
+	Sleep,
+	Stop,
+	Sysrq,
+	Tab,
+	Underline,
+	Unlabeled,
+	VolumeDown,
+	VolumeUp,
+	Wake,
+	WebBack,
+	WebFavorites,
+	WebForward,
+	WebHome,
+	WebRefresh,
+	WebSearch,
+	WebStop,
+	Yen,
+	Copy,
+	Paste,
+	Cut,
+);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_keys_and_modifiers_ignoring_case() {
+		assert_eq!(
+			"ctrl+shift+g".parse::<KeyBinding>().unwrap(),
+			KeyBinding::new(
+				Input::Key(KeyCode::G),
+				Modifiers::CTRL | Modifiers::SHIFT
+			)
+		);
+		assert_eq!(
+			"MouseBack".parse::<KeyBinding>().unwrap(),
+			KeyBinding::new(Input::Mouse(MouseInput::Back), Modifiers::empty())
+		);
+	}
+
+	#[test]
+	fn rejects_unknown_keys_and_modifiers() {
+		assert!("Hyper+A".parse::<KeyBinding>().is_err());
+		assert!("Ctrl+Nope".parse::<KeyBinding>().is_err());
+		assert!("Ctrl+".parse::<KeyBinding>().is_err());
+	}
+
+	#[test]
+	fn round_trips_every_key() {
+		for (key_code, _) in KEY_NAMES {
+			let binding =
+				KeyBinding::new(Input::Key(*key_code), Modifiers::ALT);
+			assert_eq!(binding.to_string().parse(), Ok(binding));
+		}
+	}
+
+	#[test]
+	fn round_trips_every_mouse_input() {
+		for mouse_input in MOUSE_INPUTS {
+			let binding =
+				KeyBinding::new(Input::Mouse(mouse_input), Modifiers::LOGO);
+			assert_eq!(binding.to_string().parse(), Ok(binding));
+		}
+	}
+
+	#[test]
+	fn round_trips_the_defaults_through_json() {
+		let keymap = Keymap::default();
+		let json = serde_json::to_vec(&keymap).unwrap();
+		assert_eq!(serde_json::from_slice::<Keymap>(&json).unwrap(), keymap);
+	}
+}
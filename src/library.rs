@@ -1,33 +1,509 @@
+use crate::adjustments::ImageAdjustments;
+use crate::dates::{civil_from_days, unix_now, SECONDS_PER_DAY};
+use crate::filename::{parse_filename, FileInfo};
+use crate::migrations::{migrate, CURRENT_VERSION};
+use crate::query::Query;
+use ::image::imageops::FilterType;
 use iced::widget::image;
 use serde::{Deserialize, Serialize};
 use std::{
+	collections::BTreeMap,
+	ffi::OsStr,
+	fmt,
 	fs::File,
-	io::Read,
+	io::{Cursor, Read},
 	path::{Path, PathBuf},
 	sync::{Arc, RwLock},
+	time::Duration,
 };
 use uuid::Uuid;
 use zip::ZipArchive;
 
+/// How a page is sized to fit in the viewer.
+#[derive(
+	Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default,
+)]
+pub enum FitMode {
+	/// Shrinks the page to fit the window, but never enlarges it.
+	#[default]
+	Best,
+	/// Fits the page to the width of the window and scrolls vertically.
+	Width,
+	/// Fits the page to the height of the window and scrolls horizontally.
+	Height,
+	/// Shows the page at its original size.
+	Original,
+	/// Stretches the page to fill the window.
+	Fill,
+}
+
+impl FitMode {
+	pub const ALL: [FitMode; 5] = [
+		FitMode::Best,
+		FitMode::Width,
+		FitMode::Height,
+		FitMode::Original,
+		FitMode::Fill,
+	];
+
+	pub fn next(self) -> Self {
+		let index = Self::ALL.iter().position(|m| *m == self).unwrap_or(0);
+		Self::ALL[(index + 1) % Self::ALL.len()]
+	}
+}
+
+impl fmt::Display for FitMode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			FitMode::Best => "Best fit",
+			FitMode::Width => "Fit width",
+			FitMode::Height => "Fit height",
+			FitMode::Original => "Original size",
+			FitMode::Fill => "Fill",
+		})
+	}
+}
+
+/// The filter used to downscale pages to the size they are shown at.
+#[derive(
+	Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default,
+)]
+pub enum ScalingFilter {
+	/// The sharpest, and the slowest.
+	#[default]
+	Lanczos3,
+	CatmullRom,
+	/// The fastest, but softens fine lines.
+	Triangle,
+}
+
+impl ScalingFilter {
+	pub const ALL: [ScalingFilter; 3] = [
+		ScalingFilter::Lanczos3,
+		ScalingFilter::CatmullRom,
+		ScalingFilter::Triangle,
+	];
+}
+
+impl fmt::Display for ScalingFilter {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			ScalingFilter::Lanczos3 => "Lanczos",
+			ScalingFilter::CatmullRom => "Catmull-Rom",
+			ScalingFilter::Triangle => "Bilinear",
+		})
+	}
+}
+
+impl From<ScalingFilter> for FilterType {
+	fn from(filter: ScalingFilter) -> Self {
+		match filter {
+			ScalingFilter::Lanczos3 => FilterType::Lanczos3,
+			ScalingFilter::CatmullRom => FilterType::CatmullRom,
+			ScalingFilter::Triangle => FilterType::Triangle,
+		}
+	}
+}
+
+/// How far a page is turned clockwise, for pages that were scanned sideways.
+#[derive(
+	Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default,
+)]
+pub enum Rotation {
+	#[default]
+	None,
+	Clockwise,
+	UpsideDown,
+	CounterClockwise,
+}
+
+impl Rotation {
+	pub const ALL: [Rotation; 4] = [
+		Rotation::None,
+		Rotation::Clockwise,
+		Rotation::UpsideDown,
+		Rotation::CounterClockwise,
+	];
+
+	/// The rotation turned another quarter clockwise.
+	pub fn next(self) -> Self {
+		let index = Self::ALL.iter().position(|r| *r == self).unwrap_or(0);
+		Self::ALL[(index + 1) % Self::ALL.len()]
+	}
+
+	fn apply(self, img: ::image::DynamicImage) -> ::image::DynamicImage {
+		match self {
+			Rotation::None => img,
+			Rotation::Clockwise => img.rotate90(),
+			Rotation::UpsideDown => img.rotate180(),
+			Rotation::CounterClockwise => img.rotate270(),
+		}
+	}
+}
+
+impl fmt::Display for Rotation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Rotation::None => "Not rotated",
+			Rotation::Clockwise => "Rotated 90°",
+			Rotation::UpsideDown => "Rotated 180°",
+			Rotation::CounterClockwise => "Rotated 270°",
+		})
+	}
+}
+
+/// How far the user got with a book.
+#[derive(
+	Debug,
+	Serialize,
+	Deserialize,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	Default,
+)]
+pub enum ReadStatus {
+	#[default]
+	Unread,
+	Reading,
+	Read,
+}
+
+impl ReadStatus {
+	pub const ALL: [ReadStatus; 3] =
+		[ReadStatus::Unread, ReadStatus::Reading, ReadStatus::Read];
+}
+
+impl fmt::Display for ReadStatus {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			ReadStatus::Unread => "Unread",
+			ReadStatus::Reading => "Reading",
+			ReadStatus::Read => "Read",
+		})
+	}
+}
+
+/// The highest rating a book can be given.
+pub const MAX_RATING: u8 = 5;
+
+/// Which read status the books shown in the library need to have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatusFilter {
+	#[default]
+	Any,
+	Is(ReadStatus),
+}
+
+impl StatusFilter {
+	pub const ALL: [StatusFilter; 4] = [
+		StatusFilter::Any,
+		StatusFilter::Is(ReadStatus::Unread),
+		StatusFilter::Is(ReadStatus::Reading),
+		StatusFilter::Is(ReadStatus::Read),
+	];
+}
+
+impl fmt::Display for StatusFilter {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			StatusFilter::Any => f.write_str("Any status"),
+			StatusFilter::Is(status) => status.fmt(f),
+		}
+	}
+}
+
+/// Narrows down the books shown in the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BookFilter {
+	pub favorites_only: bool,
+	pub status: StatusFilter,
+	pub min_rating: u8,
+}
+
+impl BookFilter {
+	pub fn matches(&self, book: &Book) -> bool {
+		(!self.favorites_only || book.favorite)
+			&& match self.status {
+				StatusFilter::Any => true,
+				StatusFilter::Is(status) => book.status == status,
+			} && book.rating >= self.min_rating
+	}
+}
+
+/// The order of the books shown in the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+	/// The order the books were added in.
+	#[default]
+	Added,
+	Title,
+	Author,
+	/// The best rated books first.
+	Rating,
+	Status,
+}
+
+impl SortKey {
+	pub const ALL: [SortKey; 5] = [
+		SortKey::Added,
+		SortKey::Title,
+		SortKey::Author,
+		SortKey::Rating,
+		SortKey::Status,
+	];
+}
+
+impl fmt::Display for SortKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			SortKey::Added => "Date added",
+			SortKey::Title => "Title",
+			SortKey::Author => "Author",
+			SortKey::Rating => "Rating",
+			SortKey::Status => "Status",
+		})
+	}
+}
+
+/// A named shelf of books, in the order the user put them in, or a smart
+/// collection of the books matching a saved query.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Collection {
+	id: Uuid,
+	name: String,
+	books: Vec<Uuid>,
+	/// The query of a smart collection, in the syntax of `Query`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	query: Option<String>,
+}
+
+impl Collection {
+	pub fn get_id(&self) -> Uuid {
+		self.id
+	}
+
+	pub fn get_name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn get_query(&self) -> Option<&str> {
+		self.query.as_deref()
+	}
+
+	pub fn is_smart(&self) -> bool {
+		self.query.is_some()
+	}
+
+	pub fn contains(&self, book: &Uuid) -> bool {
+		self.books.contains(book)
+	}
+}
+
+/// A page of a book the user wants to find again.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Bookmark {
+	page: usize,
+	note: Option<String>,
+}
+
+impl Bookmark {
+	pub fn get_page(&self) -> usize {
+		self.page
+	}
+
+	pub fn get_note(&self) -> &str {
+		self.note.as_ref().map(|n| n.as_ref()).unwrap_or_default()
+	}
+}
+
+/// The order in which the pages of a book are read.
+#[derive(
+	Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default,
+)]
+pub enum ReadingDirection {
+	#[default]
+	LeftToRight,
+	/// Used by most manga.
+	RightToLeft,
+}
+
+impl ReadingDirection {
+	pub const ALL: [ReadingDirection; 2] =
+		[ReadingDirection::LeftToRight, ReadingDirection::RightToLeft];
+}
+
+impl fmt::Display for ReadingDirection {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			ReadingDirection::LeftToRight => "Left to right",
+			ReadingDirection::RightToLeft => "Right to left",
+		})
+	}
+}
+
+/// Everything that changes how the pages of a book are rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageOptions {
+	pub auto_crop: AutoCrop,
+	pub adjustments: ImageAdjustments,
+	/// The area the pages are downscaled to, or `None` to keep their
+	/// original size.
+	pub display_size: Option<DisplaySize>,
+}
+
+/// The area of the window a page is shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplaySize {
+	pub width: u32,
+	pub height: u32,
+	pub fit_mode: FitMode,
+	pub filter: ScalingFilter,
+}
+
+impl DisplaySize {
+	/// Downscales the image to the size the fit mode will show it at, so
+	/// that the renderer doesn't have to.
+	fn scale(&self, img: ::image::DynamicImage) -> ::image::DynamicImage {
+		let filter = self.filter.into();
+		let (width, height) = match self.fit_mode {
+			FitMode::Best => (self.width, self.height),
+			FitMode::Width => (self.width, u32::MAX),
+			FitMode::Height => (u32::MAX, self.height),
+			FitMode::Original => return img,
+			FitMode::Fill => {
+				return img.resize_exact(self.width, self.height, filter)
+			}
+		};
+		if img.width() <= width && img.height() <= height {
+			return img;
+		}
+		img.resize(width, height, filter)
+	}
+}
+
+/// Settings for trimming the uniform borders around scanned pages.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct AutoCrop {
+	pub enabled: bool,
+	/// How far a pixel's brightness may differ from the border color and
+	/// still be trimmed.
+	pub tolerance: u8,
+}
+
+impl Default for AutoCrop {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			tolerance: 24,
+		}
+	}
+}
+
+/// The part of a page that is kept after cropping its borders.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct CropRect {
+	x: u32,
+	y: u32,
+	width: u32,
+	height: u32,
+}
+
+/// The crop rectangles found for the pages of a book, by page index.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+struct CropCache {
+	tolerance: u8,
+	rects: BTreeMap<usize, CropRect>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Book {
 	id: Uuid,
+	/// When the book was imported, in seconds since the Unix epoch.
+	#[serde(default)]
+	added: Option<u64>,
+	#[serde(default)]
+	adjustments: Option<ImageAdjustments>,
 	author: Option<String>,
+	#[serde(default)]
+	bookmarks: Vec<Bookmark>,
+	/// The chapter number or range of the book, such as `45` or `1-6`.
+	#[serde(default)]
+	chapter: Option<String>,
+	#[serde(default)]
+	crops: CropCache,
+	#[serde(default)]
+	favorite: bool,
+	#[serde(default)]
+	fit_mode: Option<FitMode>,
+	/// Whether the file was gone when the library was last checked.
+	#[serde(skip)]
+	missing: bool,
 	path: PathBuf,
+	/// From 0 to `MAX_RATING` stars, where 0 means not rated.
+	#[serde(default)]
+	rating: u8,
+	#[serde(default)]
+	reading_direction: ReadingDirection,
+	#[serde(default)]
+	rotation: Rotation,
+	/// The scanlation group that released the book.
+	#[serde(default)]
+	scan_group: Option<String>,
+	/// The name of the series the book is a volume of.
+	#[serde(default)]
+	series: Option<String>,
+	/// Rotations of single pages that differ from the book's, by page index.
+	#[serde(default)]
+	page_rotations: BTreeMap<usize, Rotation>,
+	#[serde(default)]
+	split_spreads: bool,
+	/// The image files that hold two pages, by file index, once they have
+	/// been looked for.
+	#[serde(skip)]
+	spreads: Option<Vec<usize>>,
+	#[serde(default)]
+	status: ReadStatus,
 	tags: Vec<String>,
 	title: Option<String>,
+	#[serde(default)]
+	volume: Option<u32>,
+	#[serde(default)]
+	year: Option<u16>,
 	// #[serde(skip)]
 	// cover: Option<image::Handle>,
 }
 
 impl Book {
-	fn new(path: &Path) -> Self {
+	pub(crate) fn new(path: &Path) -> Self {
 		Self {
 			id: Uuid::new_v4(),
+			added: Some(unix_now()),
+			adjustments: None,
 			author: None,
+			bookmarks: Vec::new(),
+			chapter: None,
+			crops: CropCache::default(),
+			favorite: false,
+			fit_mode: None,
+			missing: false,
 			path: path.to_path_buf(),
+			rating: 0,
+			reading_direction: ReadingDirection::default(),
+			rotation: Rotation::default(),
+			scan_group: None,
+			series: None,
+			page_rotations: BTreeMap::new(),
+			split_spreads: false,
+			spreads: None,
+			status: ReadStatus::default(),
 			tags: Vec::new(),
 			title: None,
+			volume: None,
+			year: None,
 			// cover: None,
 		}
 	}
@@ -44,11 +520,15 @@ impl Book {
 		self.path.clone()
 	}
 
+	pub fn is_missing(&self) -> bool {
+		self.missing
+	}
+
 	pub fn get_title(&self) -> &str {
 		self.title
 			.as_ref()
 			.map(|t| t.as_ref())
-			.or_else(|| self.path.file_stem().and_then(|stem| stem.to_str()))
+			.or_else(|| book_name(&self.path).and_then(|name| name.to_str()))
 			.unwrap_or_default()
 	}
 
@@ -63,6 +543,245 @@ impl Book {
 	pub fn set_author(&mut self, author: String) {
 		self.author = Some(author);
 	}
+
+	pub fn get_series(&self) -> &str {
+		self.series.as_deref().unwrap_or_default()
+	}
+
+	pub fn set_series(&mut self, series: String) {
+		self.series = Some(series);
+	}
+
+	pub fn get_volume(&self) -> Option<u32> {
+		self.volume
+	}
+
+	pub fn get_chapter(&self) -> Option<&str> {
+		self.chapter.as_deref()
+	}
+
+	pub fn get_year(&self) -> Option<u16> {
+		self.year
+	}
+
+	pub fn get_scan_group(&self) -> Option<&str> {
+		self.scan_group.as_deref()
+	}
+
+	/// Fills in the details found in the file name.
+	pub fn set_file_info(&mut self, info: FileInfo) {
+		if info.series.is_some() {
+			self.series = info.series;
+		}
+		self.volume = info.volume.or(self.volume);
+		self.chapter = info.chapter.or(self.chapter.take());
+		self.year = info.year.or(self.year);
+		self.scan_group = info.group.or(self.scan_group.take());
+	}
+
+	pub fn get_rating(&self) -> u8 {
+		self.rating
+	}
+
+	pub fn set_rating(&mut self, rating: u8) {
+		self.rating = rating.min(MAX_RATING);
+	}
+
+	pub fn is_favorite(&self) -> bool {
+		self.favorite
+	}
+
+	pub fn set_favorite(&mut self, favorite: bool) {
+		self.favorite = favorite;
+	}
+
+	pub fn get_status(&self) -> ReadStatus {
+		self.status
+	}
+
+	pub fn set_status(&mut self, status: ReadStatus) {
+		self.status = status;
+	}
+
+	pub fn get_tags(&self) -> &[String] {
+		&self.tags
+	}
+
+	pub fn set_tags(&mut self, tags: Vec<String>) {
+		self.tags = tags;
+	}
+
+	pub fn get_added(&self) -> Option<u64> {
+		self.added
+	}
+
+	/// The bookmarks of the book, ordered by page.
+	pub fn get_bookmarks(&self) -> &Vec<Bookmark> {
+		&self.bookmarks
+	}
+
+	pub fn is_bookmarked(&self, page: usize) -> bool {
+		self.bookmarks.iter().any(|b| b.page == page)
+	}
+
+	/// Adds a bookmark for the page, or removes it if it already exists.
+	pub fn toggle_bookmark(&mut self, page: usize) {
+		match self.bookmarks.binary_search_by_key(&page, |b| b.page) {
+			Ok(index) => {
+				self.bookmarks.remove(index);
+			}
+			Err(index) => {
+				self.bookmarks.insert(index, Bookmark { page, note: None })
+			}
+		}
+	}
+
+	pub fn set_bookmark_note(&mut self, page: usize, note: String) {
+		if let Some(bookmark) =
+			self.bookmarks.iter_mut().find(|b| b.page == page)
+		{
+			bookmark.note = Some(note);
+		}
+	}
+
+	/// The filters for the pages of this book, if it doesn't use the
+	/// library's.
+	pub fn get_adjustments(&self) -> Option<ImageAdjustments> {
+		self.adjustments
+	}
+
+	pub fn set_adjustments(&mut self, adjustments: Option<ImageAdjustments>) {
+		self.adjustments = adjustments;
+	}
+
+	pub fn get_reading_direction(&self) -> ReadingDirection {
+		self.reading_direction
+	}
+
+	/// Changes the direction the book is read in, which swaps the halves of
+	/// split spreads, so their rotations and bookmarks are swapped along.
+	pub fn set_reading_direction(&mut self, direction: ReadingDirection) {
+		if direction == self.reading_direction {
+			return;
+		}
+		self.reading_direction = direction;
+		if !self.split_spreads {
+			return;
+		}
+		let Some(spreads) = &self.spreads else {
+			return;
+		};
+		// The first half of each spread, counting the halves before it.
+		let firsts = spreads
+			.iter()
+			.map(|file| file + spreads.iter().filter(|f| *f < file).count())
+			.collect::<Vec<usize>>();
+		let other_half = |page: usize| {
+			if firsts.contains(&page) {
+				page + 1
+			} else if page > 0 && firsts.contains(&(page - 1)) {
+				page - 1
+			} else {
+				page
+			}
+		};
+		self.page_rotations = std::mem::take(&mut self.page_rotations)
+			.into_iter()
+			.map(|(page, rotation)| (other_half(page), rotation))
+			.collect();
+		self.crops.rects = std::mem::take(&mut self.crops.rects)
+			.into_iter()
+			.map(|(page, rect)| (other_half(page), rect))
+			.collect();
+		for bookmark in &mut self.bookmarks {
+			bookmark.page = other_half(bookmark.page);
+		}
+		self.bookmarks.sort_by_key(|b| b.page);
+	}
+
+	pub fn get_split_spreads(&self) -> bool {
+		self.split_spreads
+	}
+
+	/// Splits or joins the spreads of the book, moving the bookmarks along
+	/// once the spreads have been found with [`find_spreads`].
+	pub fn set_split_spreads(&mut self, split_spreads: bool) {
+		if split_spreads != self.split_spreads {
+			let mut bookmarks = std::mem::take(&mut self.bookmarks);
+			for bookmark in &mut bookmarks {
+				bookmark.page =
+					self.page_in_layout(bookmark.page, split_spreads);
+			}
+			// Both halves of a joined spread end up on the same page.
+			bookmarks.dedup_by_key(|b| b.page);
+			self.bookmarks = bookmarks;
+		}
+		self.split_spreads = split_spreads;
+		self.crops = CropCache::default();
+		self.page_rotations.clear();
+	}
+
+	/// The index the page has once the spreads are split or joined, which
+	/// is the first half of a split spread.
+	///
+	/// Pages are left as they are until the spreads have been found.
+	pub fn page_in_layout(&self, page: usize, split_spreads: bool) -> usize {
+		let Some(spreads) = &self.spreads else {
+			return page;
+		};
+		if split_spreads == self.split_spreads {
+			page
+		} else if split_spreads {
+			page + spreads.iter().filter(|file| **file < page).count()
+		} else {
+			let mut first = 0;
+			let mut file = 0;
+			loop {
+				first += if spreads.contains(&file) { 2 } else { 1 };
+				if page < first {
+					return file;
+				}
+				file += 1;
+			}
+		}
+	}
+
+	/// The rotation of every page without one of its own.
+	pub fn get_rotation(&self) -> Rotation {
+		self.rotation
+	}
+
+	/// Rotates all the pages, including the ones that were rotated on their
+	/// own.
+	pub fn set_rotation(&mut self, rotation: Rotation) {
+		self.rotation = rotation;
+		self.page_rotations.clear();
+		self.crops = CropCache::default();
+	}
+
+	pub fn get_page_rotation(&self, page: usize) -> Rotation {
+		self.page_rotations
+			.get(&page)
+			.copied()
+			.unwrap_or(self.rotation)
+	}
+
+	pub fn set_page_rotation(&mut self, page: usize, rotation: Rotation) {
+		if rotation == self.rotation {
+			self.page_rotations.remove(&page);
+		} else {
+			self.page_rotations.insert(page, rotation);
+		}
+		self.crops.rects.remove(&page);
+	}
+
+	pub fn get_fit_mode(&self) -> Option<FitMode> {
+		self.fit_mode
+	}
+
+	pub fn set_fit_mode(&mut self, fit_mode: FitMode) {
+		self.fit_mode = Some(fit_mode);
+	}
 }
 
 pub type BookRef = Arc<RwLock<Book>>;
@@ -71,31 +790,119 @@ pub type BookRef = Arc<RwLock<Book>>;
 pub struct Library {
 	version: String,
 	books: Vec<BookRef>,
+	#[serde(default)]
+	default_fit_mode: FitMode,
+	/// Whether books were last read in fullscreen.
+	#[serde(default)]
+	fullscreen: bool,
+	#[serde(default)]
+	auto_crop: AutoCrop,
+	#[serde(default)]
+	adjustments: ImageAdjustments,
+	#[serde(default)]
+	scaling_filter: ScalingFilter,
+	/// The number of seconds a slideshow shows each page for.
+	#[serde(default = "default_slideshow_interval")]
+	slideshow_interval: u32,
+	#[serde(default)]
+	collections: Vec<Collection>,
+	/// Folders whose archives are imported automatically.
+	#[serde(default)]
+	watched_folders: Vec<PathBuf>,
+	/// Whether there are changes that haven't been saved yet.
+	#[serde(skip)]
+	dirty: bool,
+	/// The number of changes made since the library was loaded.
+	#[serde(skip)]
+	edits: u64,
+}
+
+fn default_slideshow_interval() -> u32 {
+	5
+}
+
+/// Why the library file couldn't be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+	/// The file was saved by a newer version of the app, which this one
+	/// can't read without losing what that version added.
+	TooNew(String),
+	/// The file couldn't be read or parsed, such as after a crash.
+	Damaged(String),
+}
+
+impl fmt::Display for LoadError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			LoadError::TooNew(msg) | LoadError::Damaged(msg) => {
+				f.write_str(msg)
+			}
+		}
+	}
+}
+
+impl From<String> for LoadError {
+	fn from(msg: String) -> Self {
+		LoadError::Damaged(msg)
+	}
 }
 
 impl Library {
-	pub async fn load(path: PathBuf) -> Result<Self, String> {
-		if let Ok(b) = tokio::fs::read(&path).await {
-			return serde_json::from_slice(&b).map_err(|err| {
-				let msg = "Unable to parse JSON file";
-				eprintln!("{msg}: {err}");
-				msg.to_owned()
-			});
+	pub async fn load(path: PathBuf) -> Result<Self, LoadError> {
+		match tokio::fs::read(&path).await {
+			Ok(b) => Self::from_json_bytes(&b),
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+				let lib = Self::default();
+				lib.clone().save(path).await?;
+				Ok(lib)
+			}
+			Err(err) => Err(report("Unable to read library file")(err).into()),
 		}
+	}
 
-		let lib = Self::default();
-		lib.clone().save(path).await?;
+	/// Replaces a damaged library file with one of its backups, keeping the
+	/// damaged file next to it with a `.damaged` extension.
+	pub async fn restore_backup(
+		backup: PathBuf,
+		path: PathBuf,
+	) -> Result<Self, LoadError> {
+		let b = tokio::fs::read(&backup)
+			.await
+			.map_err(report("Unable to read backup"))?;
+		let lib = Self::from_json_bytes(&b)?;
+		tokio::fs::rename(&path, with_suffix(&path, ".damaged"))
+			.await
+			.map_err(report("Unable to move damaged library file"))?;
+		write_atomically(&path, &b)
+			.await
+			.map_err(report("Unable to save library file"))?;
 		Ok(lib)
 	}
 
+	/// Saves the library without ever leaving a partly written file, backing
+	/// up the previous one first.
+	///
+	/// The library is written to a temporary file that is only renamed over
+	/// the library file once it is safely on disk.
 	pub async fn save(self, path: PathBuf) -> Result<(), String> {
 		let json = self.to_json_bytes()?;
-		tokio::fs::write(&path, &json).await.map_err(|err| {
-			let msg = "Unable to save library file";
-			eprintln!("{msg}: {err}");
-			msg.to_owned()
-		})?;
-		Ok(())
+		// Losing a backup is no reason to stop saving.
+		if let Err(err) = back_up(&path).await {
+			eprintln!("Unable to back up library file: {err}");
+		}
+		write_atomically(&path, &json)
+			.await
+			.map_err(report("Unable to save library file"))
+	}
+
+	/// Parses a library file, upgrading it if it was saved by an older
+	/// version of the app.
+	fn from_json_bytes(b: &[u8]) -> Result<Self, LoadError> {
+		let mut json = serde_json::from_slice(b)
+			.map_err(report("Unable to parse JSON file"))?;
+		migrate(&mut json).inspect_err(|err| eprintln!("{err}"))?;
+		Ok(serde_json::from_value(json)
+			.map_err(report("Unable to parse JSON file"))?)
 	}
 
 	pub fn to_json_bytes(&self) -> Result<Vec<u8>, String> {
@@ -106,126 +913,1061 @@ impl Library {
 		})
 	}
 
+	pub fn is_dirty(&self) -> bool {
+		self.dirty
+	}
+
+	pub fn set_dirty(&mut self, dirty: bool) {
+		self.dirty = dirty;
+	}
+
+	/// Flags a change to be saved, such as one made to one of the books.
+	pub fn mark_edited(&mut self) {
+		self.dirty = true;
+		self.edits += 1;
+	}
+
+	pub fn get_edits(&self) -> u64 {
+		self.edits
+	}
+
 	pub fn get_books(&self) -> &Vec<BookRef> {
 		&self.books
 	}
 
+	/// The books of the collection, or of the whole library, that match the
+	/// filter and query, in the given order.
+	pub fn filtered_books(
+		&self,
+		collection: Option<Uuid>,
+		filter: &BookFilter,
+		query: Option<&Query>,
+		sort: SortKey,
+	) -> Vec<BookRef> {
+		let collection = collection.and_then(|id| self.get_collection(id));
+		// Smart collections are evaluated on every call, so they always
+		// reflect the current state of the library.
+		let smart_query = collection
+			.and_then(|c| c.get_query())
+			.map(|query| Query::parse(query).map_err(|_| ()));
+		let books = match collection {
+			Some(collection) if !collection.is_smart() => collection
+				.books
+				.iter()
+				.filter_map(|id| self.get_book(id))
+				.collect(),
+			_ => self.books.clone(),
+		};
+		let now = unix_now();
+		let mut books = books
+			.into_iter()
+			.filter(|b| {
+				let book = b.read().unwrap();
+				filter.matches(&book)
+					&& query.is_none_or(|q| q.matches(&book, now))
+					&& match &smart_query {
+						Some(Ok(q)) => q.matches(&book, now),
+						// A broken saved query shows no books.
+						Some(Err(())) => false,
+						None => true,
+					}
+			})
+			.collect::<Vec<_>>();
+		match sort {
+			SortKey::Added => {}
+			SortKey::Title => books.sort_by_cached_key(|b| {
+				b.read().unwrap().get_title().to_lowercase()
+			}),
+			SortKey::Author => books.sort_by_cached_key(|b| {
+				b.read().unwrap().get_author().to_lowercase()
+			}),
+			SortKey::Rating => books.sort_by_cached_key(|b| {
+				std::cmp::Reverse(b.read().unwrap().rating)
+			}),
+			SortKey::Status => {
+				books.sort_by_cached_key(|b| b.read().unwrap().status)
+			}
+		}
+		books
+	}
+
+	/// Adds the book, filling in its series, volume and other details from
+	/// its file name.
 	pub fn add_book(&mut self, path: &Path) -> BookRef {
-		let book = Arc::new(RwLock::new(Book::new(path)));
+		let book = new_book(path);
 		let res = Arc::clone(&book);
 		self.books.push(book);
+		self.mark_edited();
 		res
 	}
 
+	/// The library's book for the file, or else a book that isn't added to
+	/// the library, such as to read a file without importing it.
+	pub fn book_for_path(&self, path: &Path) -> BookRef {
+		self.books
+			.iter()
+			.find(|b| b.read().unwrap().path == path)
+			.map(Arc::clone)
+			.unwrap_or_else(|| new_book(path))
+	}
+
+	pub fn contains_book(&self, book: &BookRef) -> bool {
+		self.books.iter().any(|b| Arc::ptr_eq(b, book))
+	}
+
 	pub fn get_book(&self, id: &Uuid) -> Option<BookRef> {
 		self.books
 			.iter()
 			.find(|b| b.read().unwrap().id == *id)
 			.map(Arc::clone)
 	}
+
+	/// The next volume of the book's series, or else the book that follows
+	/// it in the same folder, by file name.
+	pub fn next_volume(&self, book: &BookRef) -> Option<BookRef> {
+		let (path, series, volume) = {
+			let book = book.read().unwrap();
+			(book.get_path(), book.series.clone(), book.volume)
+		};
+		if let (Some(series), Some(volume)) = (series, volume) {
+			let next = self
+				.books
+				.iter()
+				.filter_map(|b| {
+					let other = b.read().unwrap();
+					let other_volume = other.volume?;
+					(other.series.as_ref() == Some(&series)
+						&& other_volume > volume)
+						.then_some((other_volume, b))
+				})
+				.min_by_key(|(volume, _)| *volume);
+			if let Some((_, next)) = next {
+				return Some(Arc::clone(next));
+			}
+		}
+		self.books
+			.iter()
+			.filter(|b| !Arc::ptr_eq(b, book))
+			.map(|b| (b.read().unwrap().get_path(), b))
+			.filter(|(p, _)| p.parent() == path.parent() && *p > path)
+			.min_by(|(a, _), (b, _)| a.cmp(b))
+			.map(|(_, b)| Arc::clone(b))
+	}
+
+	pub fn contains_path(&self, path: &Path) -> bool {
+		self.books.iter().any(|b| b.read().unwrap().path == path)
+	}
+
+	/// Flags the books whose files can no longer be found.
+	pub fn update_missing(&mut self) {
+		for book in &self.books {
+			let mut book = book.write().unwrap();
+			book.missing = !book.path.exists();
+		}
+	}
+
+	pub fn get_watched_folders(&self) -> &[PathBuf] {
+		&self.watched_folders
+	}
+
+	pub fn add_watched_folder(&mut self, folder: PathBuf) {
+		if !self.watched_folders.contains(&folder) {
+			self.watched_folders.push(folder);
+			self.mark_edited();
+		}
+	}
+
+	pub fn remove_watched_folder(&mut self, folder: &Path) {
+		let count = self.watched_folders.len();
+		self.watched_folders.retain(|f| f != folder);
+		if self.watched_folders.len() != count {
+			self.mark_edited();
+		}
+	}
+
+	pub fn get_collections(&self) -> &[Collection] {
+		&self.collections
+	}
+
+	pub fn get_collection(&self, id: Uuid) -> Option<&Collection> {
+		self.collections.iter().find(|c| c.id == id)
+	}
+
+	fn get_collection_mut(&mut self, id: Uuid) -> Option<&mut Collection> {
+		self.collections.iter_mut().find(|c| c.id == id)
+	}
+
+	pub fn add_collection(&mut self, name: String) -> Uuid {
+		let id = Uuid::new_v4();
+		self.collections.push(Collection {
+			id,
+			name,
+			books: Vec::new(),
+			query: None,
+		});
+		self.mark_edited();
+		id
+	}
+
+	/// Saves the query as a collection of the books matching it.
+	pub fn add_smart_collection(
+		&mut self,
+		name: String,
+		query: String,
+	) -> Uuid {
+		let id = Uuid::new_v4();
+		self.collections.push(Collection {
+			id,
+			name,
+			books: Vec::new(),
+			query: Some(query),
+		});
+		self.mark_edited();
+		id
+	}
+
+	pub fn rename_collection(&mut self, id: Uuid, name: String) {
+		if let Some(collection) = self.get_collection_mut(id) {
+			if collection.name != name {
+				collection.name = name;
+				self.mark_edited();
+			}
+		}
+	}
+
+	pub fn remove_collection(&mut self, id: Uuid) {
+		let count = self.collections.len();
+		self.collections.retain(|c| c.id != id);
+		if self.collections.len() != count {
+			self.mark_edited();
+		}
+	}
+
+	/// Adds the book to the end of the collection, unless it is already in
+	/// it.
+	pub fn add_to_collection(&mut self, id: Uuid, book: Uuid) {
+		if let Some(collection) = self.get_collection_mut(id) {
+			if !collection.is_smart() && !collection.books.contains(&book) {
+				collection.books.push(book);
+				self.mark_edited();
+			}
+		}
+	}
+
+	pub fn remove_from_collection(&mut self, id: Uuid, book: Uuid) {
+		if let Some(collection) = self.get_collection_mut(id) {
+			let count = collection.books.len();
+			collection.books.retain(|b| *b != book);
+			if collection.books.len() != count {
+				self.mark_edited();
+			}
+		}
+	}
+
+	/// Swaps the book with its neighbour, towards the start of the
+	/// collection if `forward` is false.
+	pub fn move_in_collection(&mut self, id: Uuid, book: Uuid, forward: bool) {
+		let Some(collection) = self.get_collection_mut(id) else {
+			return;
+		};
+		let Some(index) = collection.books.iter().position(|b| *b == book)
+		else {
+			return;
+		};
+		let other = if forward {
+			index + 1
+		} else {
+			index.wrapping_sub(1)
+		};
+		if other < collection.books.len() {
+			collection.books.swap(index, other);
+			self.mark_edited();
+		}
+	}
+
+	pub fn get_default_fit_mode(&self) -> FitMode {
+		self.default_fit_mode
+	}
+
+	pub fn set_default_fit_mode(&mut self, fit_mode: FitMode) {
+		if self.default_fit_mode != fit_mode {
+			self.default_fit_mode = fit_mode;
+			self.mark_edited();
+		}
+	}
+
+	pub fn get_auto_crop(&self) -> AutoCrop {
+		self.auto_crop
+	}
+
+	pub fn set_auto_crop(&mut self, auto_crop: AutoCrop) {
+		if self.auto_crop != auto_crop {
+			self.auto_crop = auto_crop;
+			self.mark_edited();
+		}
+	}
+
+	pub fn get_adjustments(&self) -> ImageAdjustments {
+		self.adjustments
+	}
+
+	pub fn set_adjustments(&mut self, adjustments: ImageAdjustments) {
+		if self.adjustments != adjustments {
+			self.adjustments = adjustments;
+			self.mark_edited();
+		}
+	}
+
+	pub fn get_scaling_filter(&self) -> ScalingFilter {
+		self.scaling_filter
+	}
+
+	pub fn set_scaling_filter(&mut self, filter: ScalingFilter) {
+		if self.scaling_filter != filter {
+			self.scaling_filter = filter;
+			self.mark_edited();
+		}
+	}
+
+	/// How the pages of the book are rendered in a window of the given size,
+	/// using the book's own filters over the library's.
+	pub fn page_options_for(
+		&self,
+		book: &Book,
+		width: u32,
+		height: u32,
+	) -> PageOptions {
+		let fit_mode = self.fit_mode_for(book);
+		PageOptions {
+			auto_crop: self.auto_crop,
+			adjustments: book.get_adjustments().unwrap_or(self.adjustments),
+			display_size: (fit_mode != FitMode::Original).then_some(
+				DisplaySize {
+					width,
+					height,
+					fit_mode,
+					filter: self.scaling_filter,
+				},
+			),
+		}
+	}
+
+	pub fn get_slideshow_interval(&self) -> u32 {
+		self.slideshow_interval
+	}
+
+	pub fn set_slideshow_interval(&mut self, seconds: u32) {
+		if self.slideshow_interval != seconds {
+			self.slideshow_interval = seconds;
+			self.mark_edited();
+		}
+	}
+
+	pub fn is_fullscreen(&self) -> bool {
+		self.fullscreen
+	}
+
+	pub fn set_fullscreen(&mut self, fullscreen: bool) {
+		if self.fullscreen != fullscreen {
+			self.fullscreen = fullscreen;
+			self.mark_edited();
+		}
+	}
+
+	/// The fit mode to use for the book, falling back to the library default.
+	pub fn fit_mode_for(&self, book: &Book) -> FitMode {
+		book.get_fit_mode().unwrap_or(self.default_fit_mode)
+	}
 }
 
 impl Default for Library {
 	fn default() -> Self {
 		Self {
-			version: "1.0".to_owned(),
+			version: CURRENT_VERSION.to_owned(),
 			books: Vec::new(),
+			default_fit_mode: FitMode::default(),
+			fullscreen: false,
+			auto_crop: AutoCrop::default(),
+			adjustments: ImageAdjustments::default(),
+			scaling_filter: ScalingFilter::default(),
+			slideshow_interval: default_slideshow_interval(),
+			collections: Vec::new(),
+			watched_folders: Vec::new(),
+			dirty: false,
+			edits: 0,
+		}
+	}
+}
+
+/// The number of backups kept of the library file.
+const BACKUP_COUNT: usize = 5;
+
+/// How long to wait after a backup before making another one, so that the
+/// backups reach further back than the last few autosaves.
+const BACKUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Logs the error and turns it into the message shown to the user.
+fn report<E: fmt::Display>(msg: &'static str) -> impl FnOnce(E) -> String {
+	move |err| {
+		eprintln!("{msg}: {err}");
+		msg.to_owned()
+	}
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+	let mut path = path.as_os_str().to_owned();
+	path.push(suffix);
+	path.into()
+}
+
+/// Writes the file through a temporary file that is only renamed over it once
+/// it is safely on disk.
+///
+/// Every write gets its own temporary file, so saves that overlap can't
+/// rename each other's partly written files into place.
+async fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+	let temp = with_suffix(path, &format!(".{}.tmp", Uuid::new_v4()));
+	let result = async {
+		tokio::fs::write(&temp, contents).await?;
+		tokio::fs::OpenOptions::new()
+			.write(true)
+			.open(&temp)
+			.await?
+			.sync_all()
+			.await?;
+		tokio::fs::rename(&temp, path).await?;
+		sync_parent_dir(path).await
+	}
+	.await;
+	if result.is_err() {
+		let _ = tokio::fs::remove_file(&temp).await;
+	}
+	result
+}
+
+/// The backups are kept in a folder next to the library file, named after
+/// it.
+fn backup_dir(library_file: &Path) -> PathBuf {
+	library_file.with_extension("backups")
+}
+
+/// Copies the library file into the backups folder with the current time in
+/// its name, and removes the oldest backups beyond `BACKUP_COUNT`.
+///
+/// Nothing is copied if the newest backup is less than `BACKUP_INTERVAL`
+/// old.
+/// Makes a rename in the folder of the file survive a crash.
+#[cfg(unix)]
+async fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+	let parent = match path.parent() {
+		Some(parent) if !parent.as_os_str().is_empty() => parent,
+		_ => Path::new("."),
+	};
+	tokio::fs::File::open(parent).await?.sync_all().await
+}
+
+/// Folders can't be opened as files on other platforms, where renames are
+/// left to the file system.
+#[cfg(not(unix))]
+async fn sync_parent_dir(_path: &Path) -> std::io::Result<()> {
+	Ok(())
+}
+
+async fn back_up(library_file: &Path) -> std::io::Result<()> {
+	if !library_file.exists() {
+		return Ok(());
+	}
+	if let Some(newest) = list_backups(library_file.to_path_buf()).await.first()
+	{
+		let modified = tokio::fs::metadata(newest).await?.modified()?;
+		if modified.elapsed().is_ok_and(|age| age < BACKUP_INTERVAL) {
+			return Ok(());
+		}
+	}
+	let dir = backup_dir(library_file);
+	tokio::fs::create_dir_all(&dir).await?;
+	let stem = library_file
+		.file_stem()
+		.unwrap_or_default()
+		.to_string_lossy();
+	let backup = dir.join(format!("{stem}-{}.json", timestamp(unix_now())));
+	tokio::fs::copy(library_file, backup).await?;
+
+	for old in list_backups(library_file.to_path_buf())
+		.await
+		.into_iter()
+		.skip(BACKUP_COUNT)
+	{
+		tokio::fs::remove_file(old).await?;
+	}
+	Ok(())
+}
+
+/// The backups of the library file, newest first.
+pub async fn list_backups(library_file: PathBuf) -> Vec<PathBuf> {
+	let Ok(mut entries) = tokio::fs::read_dir(backup_dir(&library_file)).await
+	else {
+		return Vec::new();
+	};
+	let mut backups = Vec::new();
+	while let Ok(Some(entry)) = entries.next_entry().await {
+		let path = entry.path();
+		if path.extension().is_some_and(|ext| ext == "json") {
+			backups.push(path);
+		}
+	}
+	// The timestamps in the names sort in the order they were made.
+	backups.sort();
+	backups.reverse();
+	backups
+}
+
+/// Formats the time as `YYYYMMDD-HHMMSS` in UTC, so that names with it sort
+/// by time.
+fn timestamp(secs: u64) -> String {
+	let (year, month, day) = civil_from_days((secs / SECONDS_PER_DAY) as i64);
+	let secs = secs % SECONDS_PER_DAY;
+	format!(
+		"{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+		secs / 3600,
+		secs / 60 % 60,
+		secs % 60
+	)
+}
+
+/// Creates a book for the file, with the details found in its name.
+fn new_book(path: &Path) -> BookRef {
+	let mut book = Book::new(path);
+	if let Some(name) = book_name(path) {
+		book.set_file_info(parse_filename(&name.to_string_lossy()));
+	}
+	Arc::new(RwLock::new(book))
+}
+
+/// The name of a book without the extension of its archive. Folders of
+/// images are named as they are, since dots in them aren't extensions.
+pub fn book_name(path: &Path) -> Option<&OsStr> {
+	if is_archive(path) {
+		path.file_stem()
+	} else {
+		path.file_name()
+	}
+}
+
+pub fn is_archive(path: &Path) -> bool {
+	path.extension()
+		.is_some_and(|ext| ext.eq_ignore_ascii_case("cbz"))
+}
+
+/// Finds every book in the folders and their subfolders, which are the
+/// archives and the folders with only images in them.
+///
+/// Folders that also hold archives or subfolders are series folders, whose
+/// images are covers and the like rather than the pages of a book.
+pub async fn scan_folders(folders: Vec<PathBuf>) -> Vec<PathBuf> {
+	let mut books = Vec::new();
+	let mut pending = folders;
+	while let Some(folder) = pending.pop() {
+		let mut has_images = false;
+		let mut has_books = false;
+		let mut entries = match tokio::fs::read_dir(&folder).await {
+			Ok(entries) => entries,
+			Err(err) => {
+				eprintln!("Unable to read folder {}: {err}", folder.display());
+				continue;
+			}
+		};
+		while let Ok(Some(entry)) = entries.next_entry().await {
+			let path = entry.path();
+			let Ok(file_type) = entry.file_type().await else {
+				continue;
+			};
+			if file_type.is_dir() {
+				has_books = true;
+				pending.push(path);
+			} else if is_archive(&path) {
+				has_books = true;
+				books.push(path);
+			} else if !has_images
+				&& supported_images_filter(&path.to_string_lossy().as_ref())
+			{
+				has_images = true;
+			}
+		}
+		if has_images && !has_books {
+			books.push(folder);
 		}
 	}
+	books.sort();
+	books
 }
 
 pub async fn load_cover_image(path: PathBuf) -> Result<image::Handle, String> {
-	let zipfile = File::open(path).map_err(|_| "Failed to read cbz file")?;
-	let mut archive =
-		ZipArchive::new(zipfile).map_err(|_| "Unable to process cbz file")?;
-
-	let first = archive
-		.file_names()
-		.filter(supported_images_filter)
-		.reduce(|res, f| if f < res { f } else { res })
-		.ok_or("Unable to find an image in the cbz file")?
-		.to_owned();
-
-	let mut img_file = archive
-		.by_name(&first)
-		.expect("First file should be present");
-	let mut b = Vec::new();
-	img_file
-		.read_to_end(&mut b)
-		.map_err(|_| "Unable to read bytes")?;
+	let mut context = open_book_images(path).await?;
+	if context.is_empty() {
+		return Err("Unable to find an image in the book".to_owned());
+	}
+	let b = read_file_bytes(&mut context, 0)?;
+	create_thumbnail(&b, 250, 350)
+}
 
-	let img = ::image::load_from_memory(&b)
+/// Loads a downscaled version of a single page of the book.
+pub async fn load_page_thumbnail(
+	book: BookRef,
+	index: usize,
+	rotation: Rotation,
+) -> Result<image::Handle, String> {
+	let mut context = get_book_image_context(&book).await?;
+	let img = rotation.apply(decode_page(&mut context, index)?);
+	Ok(resize_to_handle(img, 120, 170))
+}
+
+/// The thumbnails of some of the pages of a book, by page index.
+pub type PageThumbnails = Vec<(usize, Result<image::Handle, String>)>;
+
+/// Loads downscaled versions of several pages of the book, opening it only
+/// once.
+pub async fn load_page_thumbnails(
+	book: BookRef,
+	pages: Vec<usize>,
+) -> Result<PageThumbnails, String> {
+	let mut context = get_book_image_context(&book).await?;
+	Ok(pages
+		.into_iter()
+		.map(|index| {
+			let rotation = book.read().unwrap().get_page_rotation(index);
+			let thumbnail = decode_page(&mut context, index)
+				.map(|img| resize_to_handle(rotation.apply(img), 120, 170));
+			(index, thumbnail)
+		})
+		.collect())
+}
+
+fn create_thumbnail(
+	b: &[u8],
+	width: u32,
+	height: u32,
+) -> Result<image::Handle, String> {
+	let img = ::image::load_from_memory(b)
 		.map_err(|_| "Unable to processes image")?;
-	let img = img.resize(250, 350, ::image::imageops::FilterType::Triangle);
-	Ok(image::Handle::from_pixels(
+	Ok(resize_to_handle(img, width, height))
+}
+
+fn resize_to_handle(
+	img: ::image::DynamicImage,
+	width: u32,
+	height: u32,
+) -> image::Handle {
+	let img = img.resize(width, height, FilterType::Triangle);
+	image::Handle::from_pixels(
 		img.width(),
 		img.height(),
 		img.into_rgba8().to_vec(),
-	))
+	)
+}
+
+/// Which half of a split double-page image a page shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Half {
+	Left,
+	Right,
+}
+
+/// A page of a book, which is either a whole image file or half of one.
+#[derive(Debug, Clone, Copy)]
+struct Page {
+	file: usize,
+	half: Option<Half>,
+}
+
+/// Where the image files of a book are read from.
+enum PageSource {
+	Archive(ZipArchive<File>),
+	Folder(PathBuf),
 }
 
 pub struct BookImageContext {
-	archive: ZipArchive<File>,
+	source: PageSource,
 	filenames: Vec<String>,
+	pages: Vec<Page>,
 }
 
 impl BookImageContext {
-	fn new(archive: ZipArchive<File>, filenames: Vec<String>) -> Self {
-		Self { archive, filenames }
+	fn new(source: PageSource, filenames: Vec<String>) -> Self {
+		let pages = (0..filenames.len())
+			.map(|file| Page { file, half: None })
+			.collect();
+		Self {
+			source,
+			filenames,
+			pages,
+		}
 	}
 
+	/// The number of pages, counting both halves of split images.
 	pub fn len(&self) -> usize {
-		self.filenames.len()
+		self.pages.len()
 	}
 
 	pub fn is_empty(&self) -> bool {
-		self.filenames.is_empty()
+		self.pages.is_empty()
+	}
+
+	/// The landscape images, by file index.
+	fn find_spreads(&mut self) -> Vec<usize> {
+		(0..self.filenames.len())
+			.filter(|file| {
+				read_image_dimensions(self, *file)
+					.is_some_and(|(width, height)| width > height)
+			})
+			.collect()
 	}
+
+	/// Turns every one of the spreads into two pages, in reading order.
+	fn split_spreads(
+		&mut self,
+		spreads: &[usize],
+		direction: ReadingDirection,
+	) {
+		let halves = match direction {
+			ReadingDirection::LeftToRight => [Half::Left, Half::Right],
+			ReadingDirection::RightToLeft => [Half::Right, Half::Left],
+		};
+		let mut pages = Vec::with_capacity(self.filenames.len());
+		for file in 0..self.filenames.len() {
+			if spreads.contains(&file) {
+				pages.extend(halves.map(|half| Page {
+					file,
+					half: Some(half),
+				}));
+			} else {
+				pages.push(Page { file, half: None });
+			}
+		}
+		self.pages = pages;
+	}
+}
+
+/// Opens the image files of the book at the path, one page per file.
+async fn open_book_images(path: PathBuf) -> Result<BookImageContext, String> {
+	let (source, mut names) = if path.is_dir() {
+		let names = std::fs::read_dir(&path)
+			.map_err(|_| "Failed to read image folder")?
+			.filter_map(|entry| entry.ok())
+			.filter(|entry| entry.path().is_file())
+			.map(|entry| entry.file_name().to_string_lossy().into_owned())
+			.filter(|name| supported_images_filter(&name.as_str()))
+			.collect::<Vec<String>>();
+		(PageSource::Folder(path), names)
+	} else {
+		let zipfile =
+			File::open(path).map_err(|_| "Failed to read cbz file")?;
+		let archive = ZipArchive::new(zipfile)
+			.map_err(|_| "Unable to process cbz file")?;
+		let names = archive
+			.file_names()
+			.filter(supported_images_filter)
+			.map(|str| str.to_string())
+			.collect::<Vec<String>>();
+		(PageSource::Archive(archive), names)
+	};
+	names.sort();
+
+	Ok(BookImageContext::new(source, names))
 }
 
+/// Opens the pages of the book, with its spreads split if it asks for that.
 async fn get_book_image_context(
-	path: PathBuf,
+	book: &BookRef,
 ) -> Result<BookImageContext, String> {
-	let zipfile = File::open(path).map_err(|_| "Failed to read cbz file")?;
-	let archive =
-		ZipArchive::new(zipfile).map_err(|_| "Unable to process cbz file")?;
-
-	let mut names = archive
-		.file_names()
-		.filter(supported_images_filter)
-		.map(|str| str.to_string())
-		.collect::<Vec<String>>();
-	names.sort();
+	let (path, split_spreads, direction, spreads) = {
+		let book = book.read().unwrap();
+		(
+			book.get_path(),
+			book.split_spreads,
+			book.reading_direction,
+			book.spreads.clone(),
+		)
+	};
+	let mut context = open_book_images(path).await?;
+	if split_spreads {
+		let spreads = match spreads {
+			Some(spreads) => spreads,
+			None => {
+				let spreads = context.find_spreads();
+				book.write().unwrap().spreads = Some(spreads.clone());
+				spreads
+			}
+		};
+		context.split_spreads(&spreads, direction);
+	}
+	Ok(context)
+}
 
-	Ok(BookImageContext::new(archive, names))
+/// Looks for the spreads of the book, unless they have already been found,
+/// so that its pages can be counted either way.
+pub async fn find_spreads(book: BookRef) -> Result<(), String> {
+	if book.read().unwrap().spreads.is_some() {
+		return Ok(());
+	}
+	let path = book.read().unwrap().get_path();
+	let spreads = open_book_images(path).await?.find_spreads();
+	book.write().unwrap().spreads = Some(spreads);
+	Ok(())
 }
 
-fn load_image(
+/// How much of an archived image is read to find its size, which is enough
+/// for the headers of all but the oddest files.
+const IMAGE_HEADER_SIZE: u64 = 64 * 1024;
+
+/// Finds the size of the image file from its header, without decompressing
+/// the whole file where possible.
+fn read_image_dimensions(
 	context: &mut BookImageContext,
-	index: usize,
-) -> Result<image::Handle, String> {
+	file: usize,
+) -> Option<(u32, u32)> {
+	let filename = context.filenames.get(file)?;
+	let header = match &mut context.source {
+		PageSource::Archive(archive) => {
+			let mut b = Vec::new();
+			archive
+				.by_name(filename)
+				.ok()?
+				.take(IMAGE_HEADER_SIZE)
+				.read_to_end(&mut b)
+				.ok()?;
+			b
+		}
+		PageSource::Folder(folder) => {
+			return ::image::image_dimensions(folder.join(filename)).ok();
+		}
+	};
+	image_dimensions(&header).or_else(|| {
+		let b = read_file_bytes(context, file).ok()?;
+		image_dimensions(&b)
+	})
+}
+
+fn image_dimensions(b: &[u8]) -> Option<(u32, u32)> {
+	::image::io::Reader::new(Cursor::new(b))
+		.with_guessed_format()
+		.ok()?
+		.into_dimensions()
+		.ok()
+}
+
+fn read_file_bytes(
+	context: &mut BookImageContext,
+	file: usize,
+) -> Result<Vec<u8>, String> {
 	let filename = context
 		.filenames
-		.get(index)
+		.get(file)
 		.ok_or("Selected image not found")?;
 
-	let mut img_file = context
-		.archive
-		.by_name(filename)
-		.expect("First file should be present");
-	let mut b = Vec::new();
-	img_file
-		.read_to_end(&mut b)
-		.map_err(|_| "Unable to read bytes")?;
+	match &mut context.source {
+		PageSource::Archive(archive) => {
+			let mut img_file = archive
+				.by_name(filename)
+				.expect("First file should be present");
+			let mut b = Vec::new();
+			img_file
+				.read_to_end(&mut b)
+				.map_err(|_| "Unable to read bytes")?;
+			Ok(b)
+		}
+		PageSource::Folder(folder) => std::fs::read(folder.join(filename))
+			.map_err(|_| "Unable to read bytes".to_owned()),
+	}
+}
+
+fn get_page(context: &BookImageContext, index: usize) -> Result<Page, String> {
+	context
+		.pages
+		.get(index)
+		.copied()
+		.ok_or_else(|| "Selected image not found".to_owned())
+}
+
+/// Decodes the image of the page, cut down to its half for split images.
+fn decode_page(
+	context: &mut BookImageContext,
+	index: usize,
+) -> Result<::image::DynamicImage, String> {
+	let page = get_page(context, index)?;
+	let b = read_file_bytes(context, page.file)?;
+	let img = ::image::load_from_memory(&b)
+		.map_err(|_| "Unable to processes image")?;
+	Ok(match page.half {
+		None => img,
+		Some(half) => {
+			let half_width = img.width() / 2;
+			let x = match half {
+				Half::Left => 0,
+				Half::Right => half_width,
+			};
+			img.crop_imm(x, 0, img.width() - half_width, img.height())
+		}
+	})
+}
+
+fn load_image(
+	context: &mut BookImageContext,
+	index: usize,
+) -> Result<image::Handle, String> {
+	let page = get_page(context, index)?;
+	if page.half.is_some() {
+		let img = decode_page(context, index)?;
+		return Ok(image::Handle::from_pixels(
+			img.width(),
+			img.height(),
+			img.into_rgba8().to_vec(),
+		));
+	}
 
+	let b = read_file_bytes(context, page.file)?;
 	Ok(image::Handle::from_memory(b))
 }
 
-pub async fn load_images(path: PathBuf) -> Result<Vec<image::Handle>, String> {
-	let mut context = get_book_image_context(path).await?;
+/// Trims the borders of the page, reusing the crop rectangle found the last
+/// time the page was loaded with the same tolerance.
+fn crop_page(
+	img: ::image::DynamicImage,
+	index: usize,
+	book: &BookRef,
+	tolerance: u8,
+) -> ::image::DynamicImage {
+	let cached = {
+		let book = book.read().unwrap();
+		(book.crops.tolerance == tolerance)
+			.then(|| book.crops.rects.get(&index).copied())
+			.flatten()
+	};
+	let rect = match cached {
+		Some(rect) => rect,
+		None => {
+			let rect = find_crop_rect(&img, tolerance);
+			let mut book = book.write().unwrap();
+			if book.crops.tolerance != tolerance {
+				book.crops = CropCache {
+					tolerance,
+					rects: BTreeMap::new(),
+				};
+			}
+			book.crops.rects.insert(index, rect);
+			rect
+		}
+	};
+
+	img.crop_imm(rect.x, rect.y, rect.width, rect.height)
+}
+
+/// Loads the page, running it through the rotation, cropping, scaling and
+/// filters that are turned on.
+fn load_processed_image(
+	context: &mut BookImageContext,
+	index: usize,
+	book: &BookRef,
+	options: PageOptions,
+) -> Result<image::Handle, String> {
+	let rotation = book.read().unwrap().get_page_rotation(index);
+	if !options.auto_crop.enabled
+		&& options.adjustments.is_identity()
+		&& options.display_size.is_none()
+		&& rotation == Rotation::None
+	{
+		return load_image(context, index);
+	}
+
+	let mut img = rotation.apply(decode_page(context, index)?);
+	if options.auto_crop.enabled {
+		img = crop_page(img, index, book, options.auto_crop.tolerance);
+	}
+	if let Some(display_size) = options.display_size {
+		img = display_size.scale(img);
+	}
+	// Filtering after the scaling is cheaper, and sharpens what is shown.
+	let img = options.adjustments.apply(img);
+	Ok(image::Handle::from_pixels(
+		img.width(),
+		img.height(),
+		img.into_rgba8().to_vec(),
+	))
+}
+
+/// Finds the part of the image inside the borders that have the same color
+/// as its top left corner.
+fn find_crop_rect(img: &::image::DynamicImage, tolerance: u8) -> CropRect {
+	let luma = img.to_luma8();
+	let (width, height) = luma.dimensions();
+	let full = CropRect {
+		x: 0,
+		y: 0,
+		width,
+		height,
+	};
+	if width == 0 || height == 0 {
+		return full;
+	}
+
+	let border = luma.get_pixel(0, 0).0[0];
+	let differs =
+		|x: u32, y: u32| luma.get_pixel(x, y).0[0].abs_diff(border) > tolerance;
+	// A few stray pixels of scanner noise shouldn't stop the trimming.
+	let is_uniform = |count: u32, total: u32| count <= total / 100;
+	let row_is_uniform = |y: u32| {
+		is_uniform((0..width).filter(|x| differs(*x, y)).count() as u32, width)
+	};
+	let column_is_uniform = |x: u32, top: u32, bottom: u32| {
+		is_uniform(
+			(top..bottom).filter(|y| differs(x, *y)).count() as u32,
+			bottom - top,
+		)
+	};
+
+	let Some(top) = (0..height).find(|y| !row_is_uniform(*y)) else {
+		// The page is blank, so there is nothing to crop to.
+		return full;
+	};
+	let bottom = (top..height)
+		.rev()
+		.find(|y| !row_is_uniform(*y))
+		.map_or(height, |y| y + 1);
+	let left = (0..width)
+		.find(|x| !column_is_uniform(*x, top, bottom))
+		.unwrap_or(0);
+	let right = (left..width)
+		.rev()
+		.find(|x| !column_is_uniform(*x, top, bottom))
+		.map_or(width, |x| x + 1);
+
+	CropRect {
+		x: left,
+		y: top,
+		width: right - left,
+		height: bottom - top,
+	}
+}
+
+/// Loads a single page of the book, such as to preview new settings.
+pub async fn load_page(
+	book: BookRef,
+	index: usize,
+	options: PageOptions,
+) -> Result<image::Handle, String> {
+	let mut context = get_book_image_context(&book).await?;
+	load_processed_image(&mut context, index, &book, options)
+}
+
+/// Loads every page of the book as it is stored, without decoding it unless
+/// it is half of a split spread. Rotating, cropping, filtering and scaling
+/// are left to [`load_page`] for the pages around the one being read.
+pub async fn load_images(book: BookRef) -> Result<Vec<image::Handle>, String> {
+	let mut context = get_book_image_context(&book).await?;
 
 	let images = (0..context.len())
-		.filter_map(|index| match load_image(&mut context, index) {
+		.map(|index| load_image(&mut context, index))
+		.filter_map(|res| match res {
 			Ok(img) => Some(img),
 			Err(e) => {
 				eprintln!("{}", e);
@@ -244,10 +1986,92 @@ fn supported_images_filter(filename: &&str) -> bool {
 	let path = Path::new(filename);
 	path.file_name()
 		.map(|f| f.to_string_lossy().to_lowercase())
-		.map_or(false, |f| {
+		.is_some_and(|f| {
 			!f.starts_with('.')
 				&& (f.ends_with(".png")
 					|| f.ends_with(".jpg")
 					|| f.ends_with(".jpeg"))
 		})
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A book whose second and fourth files are spreads.
+	fn book() -> Book {
+		let mut book = Book::new(Path::new("/manga/Spreads.cbz"));
+		book.spreads = Some(vec![1, 3]);
+		book
+	}
+
+	#[test]
+	fn moves_pages_to_the_first_half_when_splitting() {
+		let book = book();
+		let pages = (0..5)
+			.map(|page| book.page_in_layout(page, true))
+			.collect::<Vec<usize>>();
+		assert_eq!(pages, [0, 1, 3, 4, 6]);
+	}
+
+	#[test]
+	fn moves_both_halves_to_the_file_when_joining() {
+		let mut book = book();
+		book.split_spreads = true;
+		let pages = (0..7)
+			.map(|page| book.page_in_layout(page, false))
+			.collect::<Vec<usize>>();
+		assert_eq!(pages, [0, 1, 1, 2, 3, 3, 4]);
+	}
+
+	#[test]
+	fn keeps_bookmarks_on_their_images() {
+		let mut book = book();
+		book.toggle_bookmark(2);
+		book.toggle_bookmark(4);
+		book.set_split_spreads(true);
+		let pages = book
+			.get_bookmarks()
+			.iter()
+			.map(Bookmark::get_page)
+			.collect::<Vec<usize>>();
+		assert_eq!(pages, [3, 6]);
+
+		book.toggle_bookmark(4);
+		book.set_split_spreads(false);
+		let pages = book
+			.get_bookmarks()
+			.iter()
+			.map(Bookmark::get_page)
+			.collect::<Vec<usize>>();
+		assert_eq!(pages, [2, 3, 4]);
+	}
+
+	#[test]
+	fn swaps_the_halves_when_changing_direction() {
+		let mut book = book();
+		book.set_split_spreads(true);
+		book.toggle_bookmark(1);
+		book.toggle_bookmark(4);
+		book.set_page_rotation(2, Rotation::Clockwise);
+		book.set_page_rotation(3, Rotation::UpsideDown);
+		book.set_reading_direction(ReadingDirection::RightToLeft);
+		let pages = book
+			.get_bookmarks()
+			.iter()
+			.map(Bookmark::get_page)
+			.collect::<Vec<usize>>();
+		assert_eq!(pages, [2, 5]);
+		assert_eq!(book.get_page_rotation(1), Rotation::Clockwise);
+		assert_eq!(book.get_page_rotation(2), Rotation::None);
+		assert_eq!(book.get_page_rotation(3), Rotation::UpsideDown);
+	}
+
+	#[test]
+	fn leaves_pages_alone_until_the_spreads_are_found() {
+		let mut book = Book::new(Path::new("/manga/Spreads.cbz"));
+		book.toggle_bookmark(4);
+		book.set_split_spreads(true);
+		assert_eq!(book.get_bookmarks()[0].get_page(), 4);
+	}
+}
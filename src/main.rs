@@ -1,27 +1,94 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
+use std::fmt;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::library::{load_cover_image, load_images, BookRef, Library};
+use crate::adjustments::ImageAdjustments;
+use crate::filename::{parse_filename, FileInfo};
+use crate::history::{
+	format_duration, history_path, log_page_turn, History, PageTurn,
+	ReadingStats,
+};
+use crate::keymap::{Action, Input, KeyBinding, Keymap, MouseInput};
+use crate::library::{
+	find_spreads, is_archive, list_backups, load_cover_image, load_images,
+	load_page, load_page_thumbnail, load_page_thumbnails, scan_folders,
+	AutoCrop, BookFilter, BookRef, FitMode, Library, LoadError, PageOptions,
+	PageThumbnails, ReadStatus, ReadingDirection, Rotation, ScalingFilter,
+	SortKey, StatusFilter, MAX_RATING,
+};
+use crate::query::Query;
+use crate::watcher::watch_folders;
 use clap::Parser;
 use iced::alignment::{Horizontal, Vertical};
 use iced::widget::{
-	button, column, container, horizontal_space, image, row, scrollable, svg,
-	text, text_input, vertical_space, Column, Row,
+	button, checkbox, column, container, horizontal_space, image, mouse_area,
+	pick_list, progress_bar, row, scrollable, slider, svg, text, text_input,
+	vertical_space, Column, Row,
 };
 use iced::{
-	event, keyboard, subscription, theme, window, Alignment, Application,
-	Color, Command, ContentFit, Element, Event, Length, Renderer, Settings,
-	Subscription, Theme,
+	event, keyboard, mouse, subscription, theme, time, window, Alignment,
+	Application, Color, Command, ContentFit, Element, Event, Length, Renderer,
+	Settings, Subscription, Theme, Vector,
 };
-use native_dialog::FileDialog;
+use native_dialog::{FileDialog, MessageDialog, MessageType};
 use uuid::Uuid;
 
+pub mod adjustments;
+pub mod dates;
+pub mod filename;
+pub mod history;
+pub mod keymap;
 pub mod library;
+pub mod migrations;
+pub mod query;
+pub mod watcher;
 
 const INIT_WIN_HEIGHT: u32 = 768;
 const INIT_WIN_WIDTH: u32 = 1024;
+/// The number of pages skipped with PageUp and PageDown.
+const PAGE_SKIP: usize = 10;
+/// How long the viewer controls stay visible in fullscreen after the mouse
+/// stops moving.
+const FULLSCREEN_CONTROLS_TIMEOUT: Duration = Duration::from_secs(2);
+/// How far a touchpad has to scroll past the edge of the page to turn it.
+const SWIPE_DISTANCE: f32 = 120.0;
+/// How long the window size has to stay the same before the pages are
+/// scaled to it.
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(300);
+/// How long the filters have to stay the same before the page is previewed
+/// with them, so that dragging a slider doesn't process it at every step.
+const PREVIEW_DEBOUNCE: Duration = Duration::from_millis(150);
+/// How long the watched folders have to stay unchanged before they are
+/// scanned, so that files are done copying.
+const FOLDER_SCAN_DEBOUNCE: Duration = Duration::from_secs(2);
+/// How long the library has to go without edits before it is saved.
+const AUTOSAVE_DEBOUNCE: Duration = Duration::from_secs(2);
+/// How many pages after the current one are scaled to the window ahead of
+/// time, so that turning the page doesn't wait for it.
+const SCALED_PAGES_AHEAD: usize = 2;
+/// How much each step of zooming in enlarges the page.
+const ZOOM_STEP: f32 = 1.25;
+/// How far the page can be zoomed in, relative to the width it is shown at.
+const MAX_ZOOM: f32 = 8.0;
+/// How many thumbnails on either side of the ones in view are loaded ahead
+/// of scrolling to them.
+const THUMBNAILS_AHEAD: usize = 20;
+/// The width a thumbnail takes in the filmstrip, and the size it takes in
+/// the overview, roughly.
+const FILMSTRIP_THUMBNAIL_WIDTH: f32 = 90.0;
+const OVERVIEW_THUMBNAIL_WIDTH: u16 = 140;
+const OVERVIEW_ROW_HEIGHT: f32 = 150.0;
+/// The heights of the controls below the page in the viewer, and the
+/// spacing between them, which the page is scaled to leave room for.
+const CONTROLS_ROW_HEIGHT: f32 = 40.0;
+const PAGE_SLIDER_HEIGHT: f32 = 22.0;
+const FILMSTRIP_HEIGHT: f32 = 130.0;
+const ADJUSTMENTS_HEIGHT: f32 = 110.0;
+const CONTROLS_SPACING: f32 = 10.0;
 
 fn main() -> iced::Result {
 	let flags = Flags::parse();
@@ -30,6 +97,8 @@ fn main() -> iced::Result {
 			size: (INIT_WIN_WIDTH, INIT_WIN_HEIGHT),
 			..window::Settings::default()
 		},
+		// Unsaved changes are saved before the window closes.
+		exit_on_close_request: false,
 		..Settings::with_flags(flags)
 	})
 }
@@ -40,11 +109,25 @@ fn default_library_path() -> PathBuf {
 	path
 }
 
+fn default_keymap_path() -> PathBuf {
+	let mut path = env::current_dir().expect("Should have a current directory");
+	path.push("keymap.json");
+	path
+}
+
+fn page_scrollable_id() -> scrollable::Id {
+	scrollable::Id::new("page")
+}
+
 #[derive(Debug, Parser)]
 struct Flags {
 	/// The location of the library file.
 	#[arg(short, long, default_value = default_library_path().into_os_string())]
 	library_file: PathBuf,
+
+	/// The location of the key bindings file.
+	#[arg(short, long, default_value = default_keymap_path().into_os_string())]
+	keymap_file: PathBuf,
 }
 
 #[derive(Debug, Clone)]
@@ -56,20 +139,212 @@ enum AppState {
 		book: BookRef,
 	},
 	Errored(String),
+	/// Shows how far the import of a folder has got.
+	FolderImport {
+		progress: ImportProgress,
+	},
+	History {
+		stats: ReadingStats,
+	},
+	/// Shows what was found in the names of the files being imported, before
+	/// adding them.
+	ImportPreview {
+		files: Vec<ImportCandidate>,
+	},
 	Library,
 	Loading,
+	/// Offers to restore a backup of a library file that couldn't be loaded.
+	RestoreBackup {
+		error: String,
+		/// Newest first.
+		backups: Vec<PathBuf>,
+	},
+	Settings {
+		/// The action waiting for a key to be pressed to bind to it.
+		capturing: Option<Action>,
+	},
 	Viewer {
 		book: BookRef,
 		cur: usize,
+		/// The pages as they are stored in the book.
 		images: Vec<image::Handle>,
+		page_input: String,
+		/// Lazily loaded page thumbnails; empty until first requested.
+		thumbnails: Vec<Option<image::Handle>>,
+		/// The pages whose thumbnails have been requested.
+		requested_thumbnails: HashSet<usize>,
+		/// How far the filmstrip or the overview is scrolled, from 0 to 1.
+		thumbnails_scrolled: f32,
+		show_overview: bool,
+		/// The filters from when the adjustments panel was opened, while it
+		/// is open.
+		editing_adjustments: Option<ImageAdjustments>,
+		slideshow: Option<Slideshow>,
+		/// How far the page is zoomed in, where 1 shows it as the fit mode
+		/// does.
+		zoom: f32,
+		/// The pages that have been processed for the current settings and
+		/// size of the window, or are being.
+		scaled: HashSet<usize>,
+		/// The processed pages around the current one, by index, shown in
+		/// place of the stored ones.
+		processed: HashMap<usize, image::Handle>,
 	},
 }
 
+/// A file picked for import, with the details found in its name.
+#[derive(Debug, Clone)]
+struct ImportCandidate {
+	path: PathBuf,
+	info: FileInfo,
+	/// The title typed in, or empty to keep the name of the file.
+	title: String,
+	selected: bool,
+}
+
+/// How far the import of a folder has got.
+#[derive(Debug, Clone)]
+struct ImportProgress {
+	folder: PathBuf,
+	/// The number of books found, or `None` while the folder is scanned.
+	found: Option<usize>,
+	/// The number of books found that were already in the library.
+	skipped: usize,
+	imported: usize,
+	/// The books that couldn't be imported, with the reason.
+	failures: Vec<(PathBuf, String)>,
+}
+
+impl ImportProgress {
+	fn is_finished(&self) -> bool {
+		self.found.is_some_and(|found| {
+			self.skipped + self.imported + self.failures.len() >= found
+		})
+	}
+}
+
+/// An entry of the collection pick lists, where no id stands for the whole
+/// library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CollectionChoice {
+	id: Option<Uuid>,
+	name: String,
+}
+
+impl fmt::Display for CollectionChoice {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.name)
+	}
+}
+
+/// The values set on every selected book by the bulk edit panel, where the
+/// empty ones are left as they are.
+#[derive(Debug, Clone, Default)]
+struct BulkEdit {
+	author: String,
+	series: String,
+	/// Comma separated, replacing the current tags.
+	tags: String,
+	direction: Option<ReadingDirection>,
+	status: Option<ReadStatus>,
+}
+
+impl BulkEdit {
+	fn apply(&self, book: &BookRef) {
+		let mut book = book.write().unwrap();
+		if !self.author.trim().is_empty() {
+			book.set_author(self.author.trim().to_owned());
+		}
+		if !self.series.trim().is_empty() {
+			book.set_series(self.series.trim().to_owned());
+		}
+		if !self.tags.trim().is_empty() {
+			book.set_tags(
+				self.tags
+					.split(',')
+					.map(|tag| tag.trim().to_owned())
+					.filter(|tag| !tag.is_empty())
+					.collect(),
+			);
+		}
+		if let Some(direction) = self.direction {
+			book.set_reading_direction(direction);
+		}
+		if let Some(status) = self.status {
+			book.set_status(status);
+		}
+	}
+}
+
+/// The timer that turns the pages of the viewer on its own.
+#[derive(Debug, Clone, Copy)]
+struct Slideshow {
+	/// The time left before the page is turned.
+	remaining: Duration,
+	/// When `remaining` was last counted down, or `None` while paused.
+	last_tick: Option<Instant>,
+}
+
 #[derive(Debug)]
 struct App {
+	bookmark_thumbnails: HashMap<(Uuid, usize), image::Handle>,
+	/// The bulk edit panel of the library, while it is open.
+	bulk_edit: Option<BulkEdit>,
+	/// The name typed in for a new or renamed collection.
+	collection_name: String,
+	/// When the library was last edited, until it is saved.
+	edited_at: Option<Instant>,
+	/// The library's edit count when the autosave timer last checked it.
+	autosaved_edits: u64,
+	/// Why the library couldn't be saved the last time it was tried.
+	save_error: Option<String>,
+	/// Whether the library is being saved.
+	saving: bool,
+	/// The save to run once the one in progress is done.
+	queued_save: Option<Message>,
+	/// When the controls were last revealed while reading in fullscreen.
+	controls_shown_at: Option<Instant>,
+	/// When a watched folder last changed, until it is scanned.
+	folders_changed_at: Option<Instant>,
+	/// Counts the folder imports started, to tell the results of the last
+	/// one apart.
+	folder_imports: u64,
+	/// When the window was last resized, until the pages are scaled to it.
+	resized_at: Option<Instant>,
+	/// When the filters were last changed, until the page is previewed with
+	/// them.
+	adjusted_at: Option<Instant>,
+	cursor_x: f32,
+	history: History,
+	history_file: PathBuf,
 	image_cache: HashMap<Uuid, image::Handle>,
+	keymap: Keymap,
+	keymap_file: PathBuf,
 	library: Library,
 	library_file: PathBuf,
+	/// Counts the times the pages of the viewer were processed anew, to
+	/// drop the pages processed for earlier settings or window sizes.
+	page_renders: u64,
+	/// Set once the library file has been read or restored from a backup,
+	/// before which the library must not be saved over it.
+	library_loaded: bool,
+	/// The collection browsed in the library, or `None` for every book.
+	library_collection: Option<Uuid>,
+	library_filter: BookFilter,
+	/// The search typed in above the library, in the syntax of `Query`.
+	library_query: String,
+	library_sort: SortKey,
+	modifiers: keyboard::Modifiers,
+	/// Set when the page scrolled in response to the last wheel event.
+	page_scrolled: bool,
+	/// The touchpad scrolling past the edge of the page so far.
+	scroll_distance: Vector,
+	/// The books selected in the library with Ctrl+click or Shift+click.
+	selected_books: HashSet<Uuid>,
+	/// The book last clicked in the library, where Shift+click selections
+	/// start from.
+	selection_anchor: Option<Uuid>,
+	show_filmstrip: bool,
 	state: AppState,
 	win_height: u32,
 	win_width: u32,
@@ -77,20 +352,124 @@ struct App {
 
 #[derive(Debug, Clone)]
 enum Message {
+	AddSelectionToCollection(CollectionChoice),
+	AddWatchedFolder,
+	AdjustmentsChanged(ImageAdjustments),
 	AdvancePage(bool),
+	ApplyBulkEdit,
+	AutoCropToggled(bool),
+	AutoCropToleranceChanged(u8),
+	AutosaveTimerTick(Instant),
+	BackupsListed(String, Vec<PathBuf>),
 	BookAuthorChanged(BookRef, String),
+	BookCollectionToggled(BookRef, Uuid, bool),
+	BookFavoriteToggled(BookRef, bool),
 	BookImagesLoaded(BookRef, Result<Vec<image::Handle>, String>),
+	BookAdjustmentsToggled(bool),
+	BookmarkNoteChanged(BookRef, usize, String),
+	BookmarkThumbnailLoaded(BookRef, usize, Result<image::Handle, String>),
+	BookRatingChanged(BookRef, u8),
+	BookReadingDirectionChanged(BookRef, ReadingDirection),
+	BookRotationChanged(BookRef, Rotation),
+	BookSeriesChanged(BookRef, String),
+	BookSplitSpreadsToggled(BookRef, bool),
+	BookStatusChanged(BookRef, ReadStatus),
+	BookTileClicked(BookRef),
+	BulkEditChanged(BulkEdit),
 	BookTitleChanged(BookRef, String),
+	BooksFound(Vec<PathBuf>),
+	CaptureBinding(Action),
+	ClearSelection,
+	CloseRequested,
+	ClosingSaveComplete(Result<(), String>),
+	CollectionNameChanged(String),
+	CollectionSelected(CollectionChoice),
+	ControlsTimerTick(Instant),
+	ConfirmImport,
 	CoverImageLoaded(BookRef, Result<image::Handle, String>),
+	CreateCollection,
+	CursorMoved(f32),
+	CycleFitMode,
+	DeleteCollection,
+	FileDropped(PathBuf),
+	FitModeSelected(FitMode),
+	FolderBookLoaded(u64, PathBuf, Result<image::Handle, String>),
+	FolderScanned(u64, Vec<PathBuf>),
+	FoundBookLoaded(PathBuf, Result<image::Handle, String>),
 	GoBack,
+	GoToLastPage,
+	GoToPage(usize),
+	HistoryLoaded(Result<History, String>),
+	ImportCandidateSeriesChanged(usize, String),
+	ImportCandidateTitleChanged(usize, String),
+	ImportCandidateToggled(usize, bool),
+	ImportFolder,
 	ImportMultipleBooks,
 	ImportSingleBook,
-	Loaded(Result<Library, String>),
+	KeyPressed(keyboard::KeyCode, keyboard::Modifiers),
+	KeymapLoaded(Result<Keymap, String>),
+	LibraryFilterChanged(BookFilter),
+	LibraryQueryChanged(String),
+	LibrarySortChanged(SortKey),
+	Loaded(Result<Library, LoadError>),
+	ModifiersChanged(keyboard::Modifiers),
+	MoveInCollection(BookRef, bool),
+	MouseButtonPressed(mouse::Button),
 	OpenBookDetails(BookRef),
 	OpenBookViewer(BookRef),
+	OpenBookmark(BookRef, usize),
+	OpenHistory,
+	OpenSettings,
+	PageClicked,
+	PageInputChanged(String),
+	PageInputSubmitted,
+	PageReloaded(BookRef, usize, u64, Result<image::Handle, String>),
+	PageRotationSelected(Rotation),
+	PageScrolled,
+	PageTurnLogged(Result<(), String>),
+	PauseSlideshow,
+	RemoveBinding(Action, KeyBinding),
+	RemoveBookmark(BookRef, usize),
+	RemoveFromCollection(BookRef),
+	RemoveWatchedFolder(PathBuf),
+	RenameCollection,
+	RestoreBackup(PathBuf),
+	ResetAdjustments,
+	ResetKeymap,
+	ResetZoom,
+	PreviewTimerTick(Instant),
+	ResizeTimerTick(Instant),
+	RotateBook,
+	RotatePage,
+	SaveKeymapComplete(Result<(), String>),
 	SaveLibrary,
 	SaveLibraryComplete(Result<(), String>),
+	SaveSettings,
+	SaveSmartCollection,
+	ScalingFilterSelected(ScalingFilter),
+	SelectAllBooks,
+	SetDefaultFitMode,
+	SkipPages(bool),
+	SlideshowIntervalChanged(u32),
+	SlideshowTick(Instant),
+	SpreadsFound(BookRef, bool, Result<(), String>),
+	ThumbnailsLoaded(BookRef, Result<PageThumbnails, String>),
+	ThumbnailsScrolled(f32),
+	ToggleAdjustments,
+	ToggleAutoCrop,
+	ToggleBookmark,
+	ToggleBulkEdit,
+	ToggleFilmstrip,
+	ToggleFullscreen,
+	ToggleOverview,
+	ToggleSlideshow,
+	ToggleSplitSpreads,
+	WatchedFolderChanged,
+	WatchTimerTick(Instant),
+	WheelScrolled(mouse::ScrollDelta),
 	WindowResized { height: u32, width: u32 },
+	ZoneClicked(MouseInput),
+	Zoom(bool),
 }
 
 impl Application for App {
@@ -102,17 +481,57 @@ impl Application for App {
 	fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
 		(
 			Self {
+				bookmark_thumbnails: HashMap::new(),
+				bulk_edit: None,
+				collection_name: String::new(),
+				edited_at: None,
+				autosaved_edits: 0,
+				save_error: None,
+				saving: false,
+				queued_save: None,
+				controls_shown_at: None,
+				folders_changed_at: None,
+				folder_imports: 0,
+				resized_at: None,
+				adjusted_at: None,
+				cursor_x: 0.0,
+				history: History::default(),
+				history_file: history_path(&flags.library_file),
 				image_cache: HashMap::new(),
+				keymap: Keymap::default(),
+				keymap_file: flags.keymap_file.clone(),
 				library: Library::default(),
 				library_file: flags.library_file.clone(),
+				library_loaded: false,
+				page_renders: 0,
+				library_collection: None,
+				library_filter: BookFilter::default(),
+				library_query: String::new(),
+				library_sort: SortKey::default(),
+				modifiers: keyboard::Modifiers::empty(),
+				page_scrolled: false,
+				scroll_distance: Vector::new(0.0, 0.0),
+				selected_books: HashSet::new(),
+				selection_anchor: None,
+				show_filmstrip: false,
 				state: AppState::Loading,
 				win_height: INIT_WIN_HEIGHT,
 				win_width: INIT_WIN_WIDTH,
 			},
-			Command::perform(
-				Library::load(flags.library_file),
-				Message::Loaded,
-			),
+			Command::batch([
+				Command::perform(
+					History::load(history_path(&flags.library_file)),
+					Message::HistoryLoaded,
+				),
+				Command::perform(
+					Library::load(flags.library_file),
+					Message::Loaded,
+				),
+				Command::perform(
+					Keymap::load(flags.keymap_file),
+					Message::KeymapLoaded,
+				),
+			]),
 		)
 	}
 
@@ -134,30 +553,153 @@ impl Application for App {
 			}
 			AppState::EditBook { .. } => "Add Book".into(),
 			AppState::Errored(_) => "Ooops".into(),
+			AppState::FolderImport { .. } => "Import folder".into(),
+			AppState::History { .. } => "Reading history".into(),
+			AppState::ImportPreview { .. } => "Import books".into(),
 			AppState::Library => "Library".into(),
 			AppState::Loading => "Loading".into(),
+			AppState::RestoreBackup { .. } => "Restore library".into(),
+			AppState::Settings { .. } => "Settings".into(),
 			AppState::Viewer { book, .. } => {
 				book.read().unwrap().get_title().to_string()
 			}
 		};
-		format!("{subtitle} - My App")
+		// Like in editors, a star marks unsaved changes.
+		let unsaved = if self.library.is_dirty() { "*" } else { "" };
+		match &self.save_error {
+			Some(err) => format!("{unsaved}{subtitle} ({err}) - My App"),
+			None => format!("{unsaved}{subtitle} - My App"),
+		}
 	}
 
 	fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
 		match message {
-			Message::AdvancePage(go_forward) => match &mut self.state {
-				AppState::Viewer { cur, images, .. } => {
-					if !go_forward && *cur > 0 {
-						*cur -= 1;
-					} else if go_forward && *cur < images.len() - 1 {
-						*cur += 1;
+			Message::AddSelectionToCollection(CollectionChoice {
+				id: Some(id),
+				..
+			}) => {
+				// Keep the order the books are shown in.
+				let books = self.shown_books();
+				for book in books {
+					let book = book.read().unwrap().get_id();
+					if self.selected_books.contains(&book) {
+						self.library.add_to_collection(id, book);
 					}
-					Command::none()
+				}
+				self.selected_books.clear();
+				Command::none()
+			}
+			Message::AddSelectionToCollection(_) => Command::none(),
+			Message::AddWatchedFolder => {
+				let folder = FileDialog::new().show_open_single_dir().unwrap();
+				match folder {
+					Some(folder) => {
+						self.library.add_watched_folder(folder);
+						self.scan_watched_folders()
+					}
+					None => Command::none(),
+				}
+			}
+			Message::AdjustmentsChanged(adjustments) => {
+				if let AppState::Viewer { book, .. } = &self.state {
+					let mut book = book.write().unwrap();
+					if book.get_adjustments().is_some() {
+						book.set_adjustments(Some(adjustments));
+						self.library.mark_edited();
+					} else {
+						self.library.set_adjustments(adjustments);
+					}
+				}
+				self.adjusted_at = Some(Instant::now());
+				Command::none()
+			}
+			Message::AdvancePage(go_forward) => match &self.state {
+				AppState::Viewer { cur, .. } => {
+					let index = if go_forward {
+						cur.saturating_add(1)
+					} else {
+						cur.saturating_sub(1)
+					};
+					self.go_to_page(index)
 				}
 				_ => Command::none(),
 			},
+			Message::ApplyBulkEdit => {
+				if let Some(bulk_edit) = self.bulk_edit.take() {
+					for book in self.library.get_books() {
+						let id = book.read().unwrap().get_id();
+						if self.selected_books.contains(&id) {
+							bulk_edit.apply(book);
+						}
+					}
+					self.library.mark_edited();
+				}
+				Command::none()
+			}
+			Message::AutoCropToggled(enabled) => {
+				let auto_crop = self.library.get_auto_crop();
+				self.library.set_auto_crop(AutoCrop {
+					enabled,
+					..auto_crop
+				});
+				match &self.state {
+					AppState::Viewer { book, cur, .. } => {
+						let (book, cur) = (Arc::clone(book), *cur);
+						self.open_book_viewer(book, cur)
+					}
+					_ => Command::none(),
+				}
+			}
+			Message::AutoCropToleranceChanged(tolerance) => {
+				let auto_crop = self.library.get_auto_crop();
+				self.library.set_auto_crop(AutoCrop {
+					tolerance,
+					..auto_crop
+				});
+				Command::none()
+			}
+			Message::AutosaveTimerTick(now) => {
+				// The library is saved once it has gone unchanged for a while.
+				let edits = self.library.get_edits();
+				if edits != self.autosaved_edits || self.edited_at.is_none() {
+					self.autosaved_edits = edits;
+					self.edited_at = Some(now);
+				}
+				match self.edited_at {
+					Some(edited_at)
+						if now.duration_since(edited_at)
+							>= AUTOSAVE_DEBOUNCE =>
+					{
+						self.save_library()
+					}
+					_ => Command::none(),
+				}
+			}
+			Message::BackupsListed(error, backups) => {
+				self.state = if backups.is_empty() {
+					AppState::Errored(error)
+				} else {
+					AppState::RestoreBackup { error, backups }
+				};
+				Command::none()
+			}
 			Message::BookAuthorChanged(book, author) => {
 				book.write().unwrap().set_author(author);
+				self.library.mark_edited();
+				Command::none()
+			}
+			Message::BookCollectionToggled(book, collection, added) => {
+				let book = book.read().unwrap().get_id();
+				if added {
+					self.library.add_to_collection(collection, book);
+				} else {
+					self.library.remove_from_collection(collection, book);
+				}
+				Command::none()
+			}
+			Message::BookFavoriteToggled(book, favorite) => {
+				book.write().unwrap().set_favorite(favorite);
+				self.library.mark_edited();
 				Command::none()
 			}
 			Message::BookImagesLoaded(book, Ok(images)) => {
@@ -166,22 +708,211 @@ impl Application for App {
 						book: current_book,
 						cur,
 						images: current_images,
+						..
 					} if *current_book.read().unwrap()
 						== *book.read().unwrap() =>
 					{
-						*cur = 0;
+						*cur = (*cur).min(images.len().saturating_sub(1));
 						*current_images = images;
-						Command::none()
+						let scale = self.rerender_pages();
+						if self.show_filmstrip {
+							return Command::batch([
+								scale,
+								self.request_thumbnails(),
+							]);
+						}
+						scale
 					}
 					_ => Command::none(),
 				}
 			}
 			Message::BookImagesLoaded(_book, Err(e)) => {
 				self.state = AppState::Errored(e);
+				self.leave_fullscreen()
+			}
+			Message::BookAdjustmentsToggled(own) => {
+				if let AppState::Viewer { book, .. } = &self.state {
+					book.write().unwrap().set_adjustments(
+						own.then(|| self.library.get_adjustments()),
+					);
+					self.library.mark_edited();
+				}
+				self.preview_page()
+			}
+			Message::BookmarkNoteChanged(book, page, note) => {
+				book.write().unwrap().set_bookmark_note(page, note);
+				self.library.mark_edited();
+				Command::none()
+			}
+			Message::BookmarkThumbnailLoaded(book, page, Ok(img)) => {
+				let id = { book.read().unwrap().get_id() };
+				self.bookmark_thumbnails.insert((id, page), img);
+				Command::none()
+			}
+			Message::BookmarkThumbnailLoaded(_book, page, Err(e)) => {
+				eprintln!("Unable to load thumbnail for page {page}: {e}");
+				Command::none()
+			}
+			Message::BookRatingChanged(book, rating) => {
+				book.write().unwrap().set_rating(rating);
+				self.library.mark_edited();
+				Command::none()
+			}
+			Message::BookReadingDirectionChanged(book, direction) => {
+				book.write().unwrap().set_reading_direction(direction);
+				self.library.mark_edited();
+				self.forget_bookmark_thumbnails(&book);
+				Command::none()
+			}
+			Message::BookRotationChanged(book, rotation) => {
+				book.write().unwrap().set_rotation(rotation);
+				self.library.mark_edited();
+				self.forget_bookmark_thumbnails(&book);
+				match &mut self.state {
+					AppState::BookDetails { .. } => {
+						self.open_book_details(book)
+					}
+					AppState::Viewer {
+						thumbnails,
+						requested_thumbnails,
+						..
+					} => {
+						thumbnails.clear();
+						requested_thumbnails.clear();
+						let reload = self.rerender_pages();
+						if self.show_filmstrip {
+							return Command::batch([
+								reload,
+								self.request_thumbnails(),
+							]);
+						}
+						reload
+					}
+					_ => Command::none(),
+				}
+			}
+			Message::BookSplitSpreadsToggled(book, split_spreads) => {
+				// The spreads have to be known to move the bookmarks and the
+				// viewer to the same pages in the new layout.
+				Command::perform(find_spreads(Arc::clone(&book)), move |res| {
+					Message::SpreadsFound(book, split_spreads, res)
+				})
+			}
+			Message::BookStatusChanged(book, status) => {
+				book.write().unwrap().set_status(status);
+				self.library.mark_edited();
+				Command::none()
+			}
+			Message::BookSeriesChanged(book, series) => {
+				book.write().unwrap().set_series(series);
+				self.library.mark_edited();
+				Command::none()
+			}
+			Message::BookTileClicked(book) => {
+				let id = book.read().unwrap().get_id();
+				if self.modifiers.shift() {
+					self.select_range(id);
+				} else if self.modifiers.control() {
+					if !self.selected_books.remove(&id) {
+						self.selected_books.insert(id);
+					}
+				} else {
+					return self.open_book_details(book);
+				}
+				self.selection_anchor = Some(id);
+				Command::none()
+			}
+			Message::BulkEditChanged(bulk_edit) => {
+				self.bulk_edit = Some(bulk_edit);
 				Command::none()
 			}
 			Message::BookTitleChanged(book, title) => {
 				book.write().unwrap().set_title(title);
+				self.library.mark_edited();
+				Command::none()
+			}
+			Message::BooksFound(paths) => {
+				self.library.update_missing();
+				// Like with folder imports, books are only added once they
+				// are known to load, so that a broken or half copied file
+				// doesn't end up in the library.
+				let commands = paths
+					.into_iter()
+					.filter(|path| !self.library.contains_path(path))
+					.map(|path| {
+						Command::perform(
+							load_cover_image(path.clone()),
+							move |res| Message::FoundBookLoaded(path, res),
+						)
+					});
+				Command::batch(commands)
+			}
+			Message::CaptureBinding(action) => {
+				if let AppState::Settings { capturing } = &mut self.state {
+					*capturing = Some(action);
+				}
+				Command::none()
+			}
+			Message::CloseRequested => {
+				// Until a library file has been read, saving would replace
+				// it with the empty library shown in the meantime.
+				if !self.library_loaded {
+					return window::close();
+				}
+				if self.saving {
+					self.queued_save = Some(Message::CloseRequested);
+					return Command::none();
+				}
+				if !self.library.is_dirty() {
+					return window::close();
+				}
+				self.saving = true;
+				self.library.set_dirty(false);
+				Command::perform(
+					self.library.clone().save(self.library_file.clone()),
+					Message::ClosingSaveComplete,
+				)
+			}
+			Message::ClosingSaveComplete(Ok(_)) => window::close(),
+			Message::ClosingSaveComplete(Err(e)) => {
+				self.saving = false;
+				self.library.set_dirty(true);
+				let close = MessageDialog::new()
+					.set_type(MessageType::Warning)
+					.set_title("Unsaved changes")
+					.set_text(&format!(
+						"{e}. Close anyway and lose the unsaved changes?"
+					))
+					.show_confirm()
+					.unwrap_or(false);
+				if close {
+					window::close()
+				} else {
+					Command::none()
+				}
+			}
+			Message::ClearSelection => {
+				self.selected_books.clear();
+				self.selection_anchor = None;
+				self.bulk_edit = None;
+				Command::none()
+			}
+			Message::CollectionNameChanged(name) => {
+				self.collection_name = name;
+				Command::none()
+			}
+			Message::CollectionSelected(choice) => {
+				self.library_collection = choice.id;
+				Command::none()
+			}
+			Message::ControlsTimerTick(now) => {
+				if let Some(shown_at) = self.controls_shown_at {
+					if now.duration_since(shown_at)
+						>= FULLSCREEN_CONTROLS_TIMEOUT
+					{
+						self.controls_shown_at = None;
+					}
+				}
 				Command::none()
 			}
 			Message::CoverImageLoaded(book, Ok(img)) => {
@@ -189,51 +920,284 @@ impl Application for App {
 				self.image_cache.insert(id, img);
 				Command::none()
 			}
-			Message::CoverImageLoaded(_book, Err(e)) => {
-				self.state = AppState::Errored(e);
+			Message::CoverImageLoaded(book, Err(e)) => {
+				// A removed file is flagged in the library rather than
+				// treated as an error.
+				if book.read().unwrap().get_path().exists() {
+					self.state = AppState::Errored(e);
+				} else {
+					eprintln!("{e}");
+				}
+				Command::none()
+			}
+			Message::CreateCollection => {
+				let name = std::mem::take(&mut self.collection_name);
+				if !name.trim().is_empty() {
+					self.library_collection =
+						Some(self.library.add_collection(name));
+				}
+				Command::none()
+			}
+			Message::CursorMoved(x) => {
+				self.cursor_x = x;
+				if self.is_reading_fullscreen() {
+					self.controls_shown_at = Some(Instant::now());
+				}
+				Command::none()
+			}
+			Message::CycleFitMode => {
+				let AppState::Viewer { book, .. } = &self.state else {
+					return Command::none();
+				};
+				let fit_mode = self.library.fit_mode_for(&book.read().unwrap());
+				book.write().unwrap().set_fit_mode(fit_mode.next());
+				self.library.mark_edited();
+				self.rerender_pages()
+			}
+			Message::DeleteCollection => {
+				if let Some(id) = self.library_collection.take() {
+					self.library.remove_collection(id);
+				}
+				Command::none()
+			}
+			Message::FileDropped(path) => {
+				if !path.is_dir() && !is_archive(&path) {
+					eprintln!("Unsupported file {}", path.display());
+					return Command::none();
+				}
+				match self.state {
+					// A folder may hold a whole series rather than a single
+					// book, so it is imported like one picked to import.
+					AppState::Library if path.is_dir() => {
+						self.import_folder(path)
+					}
+					AppState::Viewer { .. } if path.is_dir() => {
+						Command::batch([
+							self.leave_fullscreen(),
+							self.import_folder(path),
+						])
+					}
+					AppState::Library => {
+						self.update(Message::BooksFound(vec![path]))
+					}
+					AppState::Viewer { .. } => {
+						let book = self.library.book_for_path(&path);
+						self.open_book_viewer(book, 0)
+					}
+					_ => Command::none(),
+				}
+			}
+			Message::FitModeSelected(fit_mode) => {
+				let AppState::Viewer { book, .. } = &self.state else {
+					return Command::none();
+				};
+				if book.read().unwrap().get_fit_mode() == Some(fit_mode) {
+					return Command::none();
+				}
+				book.write().unwrap().set_fit_mode(fit_mode);
+				self.library.mark_edited();
+				self.rerender_pages()
+			}
+			// Starting another import drops what is left of the earlier one.
+			Message::FolderBookLoaded(import, ..)
+			| Message::FolderScanned(import, _)
+				if import != self.folder_imports =>
+			{
+				Command::none()
+			}
+			Message::FolderBookLoaded(_import, path, res) => {
+				let progress = match &mut self.state {
+					AppState::FolderImport { progress } => Some(progress),
+					_ => None,
+				};
+				match res {
+					// The book is only added once it is known to load, and
+					// the import carries on in the background after leaving
+					// the progress view.
+					Ok(cover) => {
+						if let Some(progress) = progress {
+							progress.imported += 1;
+						}
+						if !self.library.contains_path(&path) {
+							let book = self.library.add_book(&path);
+							let id = book.read().unwrap().get_id();
+							self.image_cache.insert(id, cover);
+						}
+					}
+					Err(e) => {
+						eprintln!("Unable to import {}: {e}", path.display());
+						if let Some(progress) = progress {
+							progress.failures.push((path, e));
+						}
+					}
+				}
+				Command::none()
+			}
+			Message::FoundBookLoaded(path, Ok(cover)) => {
+				if !self.library.contains_path(&path) {
+					let book = self.library.add_book(&path);
+					let id = book.read().unwrap().get_id();
+					self.image_cache.insert(id, cover);
+				}
+				Command::none()
+			}
+			Message::FoundBookLoaded(path, Err(e)) => {
+				eprintln!("Unable to import {}: {e}", path.display());
 				Command::none()
 			}
+			Message::FolderScanned(import, found) => {
+				let count = found.len();
+				let paths = found
+					.into_iter()
+					.filter(|path| !self.library.contains_path(path))
+					.collect::<Vec<PathBuf>>();
+				if let AppState::FolderImport { progress } = &mut self.state {
+					progress.found = Some(count);
+					progress.skipped = count - paths.len();
+				}
+				let commands = paths.into_iter().map(|path| {
+					Command::perform(
+						load_cover_image(path.clone()),
+						move |res| Message::FolderBookLoaded(import, path, res),
+					)
+				});
+				Command::batch(commands)
+			}
 			Message::GoBack => {
-				match &self.state {
-					AppState::BookDetails { .. } => {
+				match &mut self.state {
+					AppState::BookDetails { .. }
+					| AppState::FolderImport { .. }
+					| AppState::History { .. }
+					| AppState::ImportPreview { .. }
+					| AppState::Settings { .. } => {
 						self.state = AppState::Library;
 					}
+					AppState::Viewer {
+						show_overview,
+						thumbnails_scrolled,
+						..
+					} if *show_overview => {
+						*show_overview = false;
+						*thumbnails_scrolled = 0.0;
+					}
+					// Books opened by dropping their file aren't in the
+					// library, so they have no details to go back to.
+					AppState::Viewer { book, .. }
+						if !self.library.contains_book(book) =>
+					{
+						self.state = AppState::Library;
+						return self.leave_fullscreen();
+					}
 					AppState::Viewer { book, .. } => {
-						self.state = AppState::BookDetails {
-							book: Arc::clone(book),
-						};
+						let book = Arc::clone(book);
+						return Command::batch([
+							self.leave_fullscreen(),
+							self.open_book_details(book),
+						]);
 					}
 					_ => {}
 				};
 				Command::none()
 			}
+			Message::GoToLastPage => match &self.state {
+				AppState::Viewer { images, .. } => {
+					self.go_to_page(images.len().saturating_sub(1))
+				}
+				_ => Command::none(),
+			},
+			Message::GoToPage(index) => self.go_to_page(index),
+			Message::HistoryLoaded(Ok(history)) => {
+				self.history = history;
+				Command::none()
+			}
+			Message::HistoryLoaded(Err(e)) => {
+				// Reading shouldn't depend on the statistics, so carry on
+				// with a fresh history.
+				eprintln!("Unable to load reading history: {e}");
+				Command::none()
+			}
+			Message::ImportCandidateSeriesChanged(index, series) => {
+				if let AppState::ImportPreview { files } = &mut self.state {
+					if let Some(file) = files.get_mut(index) {
+						file.info.series = Some(series);
+					}
+				}
+				Command::none()
+			}
+			Message::ImportCandidateTitleChanged(index, title) => {
+				if let AppState::ImportPreview { files } = &mut self.state {
+					if let Some(file) = files.get_mut(index) {
+						file.title = title;
+					}
+				}
+				Command::none()
+			}
+			Message::ImportCandidateToggled(index, selected) => {
+				if let AppState::ImportPreview { files } = &mut self.state {
+					if let Some(file) = files.get_mut(index) {
+						file.selected = selected;
+					}
+				}
+				Command::none()
+			}
+			Message::ImportFolder => {
+				let folder = FileDialog::new().show_open_single_dir().unwrap();
+				match folder {
+					Some(folder) => self.import_folder(folder),
+					None => Command::none(),
+				}
+			}
 			Message::ImportMultipleBooks => {
 				let paths = FileDialog::new()
 					.add_filter("Books", &["cbz"])
 					.show_open_multiple_file()
 					.unwrap();
-				let books = paths
-					.iter()
-					.map(|p| self.library.add_book(p))
-					.filter(|b| {
-						!self
-							.image_cache
-							.contains_key(&b.read().unwrap().get_id())
-					})
-					.collect::<Vec<BookRef>>();
-
-				if books.is_empty() {
+				if paths.is_empty() {
 					return Command::none();
 				}
-				let commands = books.into_iter().map(|book| {
-					let path = {
-						let book = book.read().unwrap();
-						book.get_path()
-					};
-					Command::perform(load_cover_image(path), move |res| {
-						Message::CoverImageLoaded(book, res)
+				let files = paths
+					.into_iter()
+					.map(|path| ImportCandidate {
+						info: path
+							.file_stem()
+							.map(|stem| parse_filename(&stem.to_string_lossy()))
+							.unwrap_or_default(),
+						path,
+						title: String::new(),
+						selected: true,
 					})
-				});
+					.collect();
+				self.state = AppState::ImportPreview { files };
+				Command::none()
+			}
+			Message::ConfirmImport => {
+				let AppState::ImportPreview { files } = &mut self.state else {
+					return Command::none();
+				};
+				let files = std::mem::take(files);
+				self.state = AppState::Library;
+				let mut commands = Vec::new();
+				for file in files {
+					if !file.selected || self.library.contains_path(&file.path)
+					{
+						continue;
+					}
+					let book = self.library.add_book(&file.path);
+					{
+						let mut book = book.write().unwrap();
+						if !file.title.trim().is_empty() {
+							book.set_title(file.title);
+						}
+						let series = file.info.series.unwrap_or_default();
+						if series != book.get_series() {
+							book.set_series(series);
+						}
+					}
+					commands.push(Command::perform(
+						load_cover_image(file.path),
+						move |res| Message::CoverImageLoaded(book, res),
+					));
+				}
 				Command::batch(commands)
 			}
 			Message::ImportSingleBook => {
@@ -260,8 +1224,37 @@ impl Application for App {
 				}
 				Command::none()
 			}
+			Message::KeyPressed(key_code, modifiers) => {
+				if is_modifier_key(key_code) {
+					return Command::none();
+				}
+				self.handle_input(Input::Key(key_code), modifiers)
+			}
+			Message::KeymapLoaded(Ok(keymap)) => {
+				self.keymap = keymap;
+				Command::none()
+			}
+			Message::KeymapLoaded(Err(e)) => {
+				// The app is still usable with the default bindings, and the
+				// file is left alone so that it can be fixed by hand.
+				eprintln!("{e}, using the default key bindings");
+				Command::none()
+			}
+			Message::LibraryFilterChanged(filter) => {
+				self.library_filter = filter;
+				Command::none()
+			}
+			Message::LibraryQueryChanged(query) => {
+				self.library_query = query;
+				Command::none()
+			}
+			Message::LibrarySortChanged(sort) => {
+				self.library_sort = sort;
+				Command::none()
+			}
 			Message::Loaded(Ok(library)) => {
 				self.library = library;
+				self.library_loaded = true;
 				self.state = AppState::Library;
 
 				let commands = self.library.get_books().iter().map(|book| {
@@ -274,66 +1267,725 @@ impl Application for App {
 						Message::CoverImageLoaded(book, res)
 					})
 				});
-				Command::batch(commands)
+				Command::batch(commands.chain([self.scan_watched_folders()]))
 			}
-			Message::Loaded(Err(e)) => {
+			// Restoring a backup would replace the newer library with an
+			// older one, losing what was added since.
+			Message::Loaded(Err(LoadError::TooNew(e))) => {
 				self.state = AppState::Errored(e);
 				Command::none()
 			}
-			Message::OpenBookDetails(book) => {
-				self.state = AppState::BookDetails { book };
+			Message::Loaded(Err(LoadError::Damaged(e))) => Command::perform(
+				list_backups(self.library_file.clone()),
+				move |backups| Message::BackupsListed(e, backups),
+			),
+			Message::ModifiersChanged(modifiers) => {
+				self.modifiers = modifiers;
 				Command::none()
 			}
-			Message::OpenBookViewer(book) => {
-				let path = book.read().unwrap().get_path();
-				self.state = AppState::Viewer {
-					book: Arc::clone(&book),
-					cur: 0,
-					images: Vec::new(),
-				};
-				Command::perform(load_images(path), move |res| {
-					Message::BookImagesLoaded(book, res)
-				})
-			}
-			Message::SaveLibrary => Command::perform(
-				self.library.clone().save(self.library_file.clone()),
-				Message::SaveLibraryComplete,
-			),
+			Message::MoveInCollection(book, forward) => {
+				if let Some(id) = self.library_collection {
+					let book = book.read().unwrap().get_id();
+					self.library.move_in_collection(id, book, forward);
+				}
+				Command::none()
+			}
+			Message::MouseButtonPressed(button) => {
+				match MouseInput::from_button(button) {
+					Some(input) => {
+						self.handle_input(Input::Mouse(input), self.modifiers)
+					}
+					None => Command::none(),
+				}
+			}
+			Message::OpenBookDetails(book) => self.open_book_details(book),
+			Message::OpenBookViewer(book) => self.open_book_viewer(book, 0),
+			Message::OpenBookmark(book, page) => {
+				self.open_book_viewer(book, page)
+			}
+			Message::OpenHistory => {
+				self.state = AppState::History {
+					stats: self.history.stats(&self.library),
+				};
+				Command::none()
+			}
+			Message::OpenSettings => {
+				self.state = AppState::Settings { capturing: None };
+				Command::none()
+			}
+			Message::PageClicked => {
+				let zone = if self.cursor_x < self.win_width as f32 / 2.0 {
+					MouseInput::LeftZone
+				} else {
+					MouseInput::RightZone
+				};
+				self.update(Message::ZoneClicked(zone))
+			}
+			Message::PageInputChanged(value) => {
+				if let AppState::Viewer { page_input, .. } = &mut self.state {
+					*page_input = value;
+				}
+				Command::none()
+			}
+			Message::PageInputSubmitted => match &mut self.state {
+				AppState::Viewer { page_input, .. } => {
+					let page = page_input.trim().parse::<usize>().ok();
+					page_input.clear();
+					match page {
+						Some(page) => self.go_to_page(page.saturating_sub(1)),
+						None => Command::none(),
+					}
+				}
+				_ => Command::none(),
+			},
+			Message::PageReloaded(book, index, renders, Ok(img)) => {
+				match &mut self.state {
+					AppState::Viewer {
+						book: current_book,
+						scaled,
+						processed,
+						..
+					} if Arc::ptr_eq(current_book, &book)
+						&& renders == self.page_renders
+						&& scaled.contains(&index) =>
+					{
+						processed.insert(index, img);
+					}
+					_ => {}
+				};
+				Command::none()
+			}
+			Message::PageReloaded(_book, index, _renders, Err(e)) => {
+				eprintln!("Unable to reload page {index}: {e}");
+				Command::none()
+			}
+			Message::PageRotationSelected(rotation) => {
+				let AppState::Viewer {
+					book,
+					cur,
+					thumbnails,
+					..
+				} = &self.state
+				else {
+					return Command::none();
+				};
+				let (book, cur) = (Arc::clone(book), *cur);
+				let reload_thumbnail = !thumbnails.is_empty();
+				book.write().unwrap().set_page_rotation(cur, rotation);
+				self.library.mark_edited();
+				self.bookmark_thumbnails
+					.remove(&(book.read().unwrap().get_id(), cur));
+
+				let reload = self.preview_page();
+				if reload_thumbnail {
+					return Command::batch([
+						reload,
+						Command::perform(
+							load_page_thumbnails(Arc::clone(&book), vec![cur]),
+							move |res| Message::ThumbnailsLoaded(book, res),
+						),
+					]);
+				}
+				reload
+			}
+			Message::PageTurnLogged(Ok(())) => Command::none(),
+			Message::PageTurnLogged(Err(e)) => {
+				eprintln!("Unable to log page turn: {e}");
+				Command::none()
+			}
+			Message::PauseSlideshow => {
+				if let AppState::Viewer {
+					slideshow: Some(slideshow),
+					..
+				} = &mut self.state
+				{
+					slideshow.last_tick = match slideshow.last_tick {
+						Some(last_tick) => {
+							slideshow.remaining = slideshow
+								.remaining
+								.saturating_sub(last_tick.elapsed());
+							None
+						}
+						None => Some(Instant::now()),
+					};
+				}
+				Command::none()
+			}
+			Message::PageScrolled => {
+				self.page_scrolled = true;
+				Command::none()
+			}
+			Message::RemoveBinding(action, binding) => {
+				self.keymap.remove_binding(action, binding);
+				Command::none()
+			}
+			Message::RemoveBookmark(book, page) => {
+				book.write().unwrap().toggle_bookmark(page);
+				self.library.mark_edited();
+				Command::none()
+			}
+			Message::RemoveFromCollection(book) => {
+				if let Some(id) = self.library_collection {
+					let book = book.read().unwrap().get_id();
+					self.library.remove_from_collection(id, book);
+				}
+				Command::none()
+			}
+			Message::RemoveWatchedFolder(folder) => {
+				self.library.remove_watched_folder(&folder);
+				Command::none()
+			}
+			Message::RestoreBackup(backup) => {
+				self.state = AppState::Loading;
+				Command::perform(
+					Library::restore_backup(backup, self.library_file.clone()),
+					Message::Loaded,
+				)
+			}
+			Message::RenameCollection => {
+				let name = std::mem::take(&mut self.collection_name);
+				if let Some(id) = self.library_collection {
+					if !name.trim().is_empty() {
+						self.library.rename_collection(id, name);
+					}
+				}
+				Command::none()
+			}
+			Message::ResetAdjustments => {
+				self.update(Message::AdjustmentsChanged(Default::default()))
+			}
+			Message::PreviewTimerTick(now) => match self.adjusted_at {
+				Some(adjusted_at)
+					if now.duration_since(adjusted_at) >= PREVIEW_DEBOUNCE =>
+				{
+					self.adjusted_at = None;
+					self.preview_page()
+				}
+				_ => Command::none(),
+			},
+			Message::ResizeTimerTick(now) => match self.resized_at {
+				Some(resized_at)
+					if now.duration_since(resized_at) >= RESIZE_DEBOUNCE =>
+				{
+					self.resized_at = None;
+					self.rerender_pages()
+				}
+				_ => Command::none(),
+			},
+			Message::RotateBook => match &self.state {
+				AppState::Viewer { book, .. } => {
+					let rotation = book.read().unwrap().get_rotation();
+					self.update(Message::BookRotationChanged(
+						Arc::clone(book),
+						rotation.next(),
+					))
+				}
+				_ => Command::none(),
+			},
+			Message::RotatePage => match &self.state {
+				AppState::Viewer { book, cur, .. } => {
+					let rotation = book.read().unwrap().get_page_rotation(*cur);
+					self.update(Message::PageRotationSelected(rotation.next()))
+				}
+				_ => Command::none(),
+			},
+			Message::ResetKeymap => {
+				self.keymap = Keymap::default();
+				Command::none()
+			}
+			Message::ResetZoom => {
+				if let AppState::Viewer { zoom, .. } = &mut self.state {
+					*zoom = 1.0;
+				}
+				self.rerender_pages()
+			}
+			Message::SaveKeymapComplete(Ok(_)) => {
+				println!("Keymap saved");
+				Command::none()
+			}
+			Message::SaveKeymapComplete(Err(e)) => {
+				self.state = AppState::Errored(e);
+				Command::none()
+			}
+			Message::SaveLibrary => self.save_library(),
 			Message::SaveLibraryComplete(Ok(_)) => {
 				println!("Library saved");
-				Command::none()
+				self.save_error = None;
+				self.run_queued_save()
 			}
 			Message::SaveLibraryComplete(Err(e)) => {
-				self.state = AppState::Errored(e);
+				// Saves mostly happen in the background, so keep the user
+				// where they are and try again with the next autosave.
+				eprintln!("{e}");
+				self.library.set_dirty(true);
+				self.edited_at.get_or_insert_with(Instant::now);
+				self.save_error = Some(e);
+				self.run_queued_save()
+			}
+			Message::SaveSettings => Command::batch([
+				Command::perform(
+					self.keymap.clone().save(self.keymap_file.clone()),
+					Message::SaveKeymapComplete,
+				),
+				self.save_library(),
+			]),
+			Message::SaveSmartCollection => {
+				let query = self.library_query.trim().to_owned();
+				if query.is_empty() || Query::parse(&query).is_err() {
+					return Command::none();
+				}
+				let name = match std::mem::take(&mut self.collection_name) {
+					name if name.trim().is_empty() => query.clone(),
+					name => name,
+				};
+				self.library_collection =
+					Some(self.library.add_smart_collection(name, query));
+				self.library_query.clear();
+				Command::none()
+			}
+			Message::ScalingFilterSelected(filter) => {
+				self.library.set_scaling_filter(filter);
+				Command::none()
+			}
+			Message::SelectAllBooks => {
+				self.selected_books.extend(
+					self.shown_books()
+						.iter()
+						.map(|book| book.read().unwrap().get_id()),
+				);
+				Command::none()
+			}
+			Message::SetDefaultFitMode => {
+				if let AppState::Viewer { book, .. } = &self.state {
+					let fit_mode =
+						self.library.fit_mode_for(&book.read().unwrap());
+					self.library.set_default_fit_mode(fit_mode);
+				}
+				Command::none()
+			}
+			Message::SkipPages(go_forward) => match &self.state {
+				AppState::Viewer { cur, .. } => {
+					let index = if go_forward {
+						cur.saturating_add(PAGE_SKIP)
+					} else {
+						cur.saturating_sub(PAGE_SKIP)
+					};
+					self.go_to_page(index)
+				}
+				_ => Command::none(),
+			},
+			Message::SlideshowIntervalChanged(seconds) => {
+				self.library.set_slideshow_interval(seconds);
+				Command::none()
+			}
+			Message::SlideshowTick(now) => {
+				let interval = self.slideshow_interval();
+				let AppState::Viewer {
+					book,
+					cur,
+					images,
+					slideshow: Some(slideshow),
+					..
+				} = &mut self.state
+				else {
+					return Command::none();
+				};
+				let Some(last_tick) = slideshow.last_tick else {
+					return Command::none();
+				};
+				slideshow.remaining = slideshow
+					.remaining
+					.saturating_sub(now.saturating_duration_since(last_tick));
+				slideshow.last_tick = Some(now);
+				if !slideshow.remaining.is_zero() {
+					return Command::none();
+				}
+				if images.is_empty() {
+					// Wait for the pages to load before counting again.
+					slideshow.remaining = interval;
+					return Command::none();
+				}
+				if *cur + 1 < images.len() {
+					let next = *cur + 1;
+					return self.go_to_page(next);
+				}
+
+				let book = Arc::clone(book);
+				match self.library.next_volume(&book) {
+					Some(next) => {
+						let load = self.open_book_viewer(next, 0);
+						if let AppState::Viewer { slideshow, .. } =
+							&mut self.state
+						{
+							*slideshow = Some(Slideshow {
+								remaining: interval,
+								last_tick: Some(now),
+							});
+						}
+						load
+					}
+					None => {
+						if let AppState::Viewer { slideshow, .. } =
+							&mut self.state
+						{
+							*slideshow = None;
+						}
+						Command::none()
+					}
+				}
+			}
+			Message::ThumbnailsLoaded(book, Ok(loaded)) => {
+				let AppState::Viewer {
+					book: current_book,
+					thumbnails,
+					..
+				} = &mut self.state
+				else {
+					return Command::none();
+				};
+				if !Arc::ptr_eq(current_book, &book) {
+					return Command::none();
+				}
+				for (index, res) in loaded {
+					match res {
+						Ok(img) => {
+							if let Some(thumbnail) = thumbnails.get_mut(index) {
+								*thumbnail = Some(img);
+							}
+						}
+						Err(e) => eprintln!(
+							"Unable to load thumbnail for page {index}: {e}"
+						),
+					}
+				}
 				Command::none()
 			}
+			Message::ThumbnailsLoaded(_book, Err(e)) => {
+				eprintln!("Unable to load thumbnails: {e}");
+				Command::none()
+			}
+			Message::ThumbnailsScrolled(offset) => {
+				if let AppState::Viewer {
+					show_overview,
+					thumbnails_scrolled,
+					..
+				} = &mut self.state
+				{
+					// Scrolling the filmstrip mustn't turn the page as well.
+					self.page_scrolled = !*show_overview;
+					*thumbnails_scrolled = offset;
+				}
+				self.request_thumbnails()
+			}
+			Message::ToggleAdjustments => {
+				let AppState::Viewer {
+					book,
+					editing_adjustments,
+					..
+				} = &mut self.state
+				else {
+					return Command::none();
+				};
+				*editing_adjustments = match editing_adjustments {
+					Some(_) => None,
+					None => Some(
+						book.read()
+							.unwrap()
+							.get_adjustments()
+							.unwrap_or(self.library.get_adjustments()),
+					),
+				};
+				// The panel takes some of the page's height, and only the
+				// current page was previewed with the new filters.
+				self.rerender_pages()
+			}
+			Message::ToggleAutoCrop => {
+				if !matches!(self.state, AppState::Viewer { .. }) {
+					return Command::none();
+				}
+				let enabled = !self.library.get_auto_crop().enabled;
+				self.update(Message::AutoCropToggled(enabled))
+			}
+			Message::ToggleBookmark => {
+				if let AppState::Viewer { book, cur, .. } = &self.state {
+					book.write().unwrap().toggle_bookmark(*cur);
+					self.library.mark_edited();
+				}
+				Command::none()
+			}
+			Message::ToggleBulkEdit => {
+				self.bulk_edit = match self.bulk_edit {
+					Some(_) => None,
+					None => Some(BulkEdit::default()),
+				};
+				Command::none()
+			}
+			Message::ToggleFilmstrip => {
+				self.show_filmstrip = !self.show_filmstrip;
+				// The filmstrip takes some of the page's height.
+				let rerender = self.rerender_pages();
+				if self.show_filmstrip {
+					return Command::batch([
+						rerender,
+						self.request_thumbnails(),
+					]);
+				}
+				rerender
+			}
+			Message::ToggleFullscreen => {
+				if !matches!(self.state, AppState::Viewer { .. }) {
+					return Command::none();
+				}
+				let fullscreen = !self.library.is_fullscreen();
+				self.library.set_fullscreen(fullscreen);
+				self.controls_shown_at = None;
+				Command::batch([
+					window::change_mode(if fullscreen {
+						window::Mode::Fullscreen
+					} else {
+						window::Mode::Windowed
+					}),
+					self.save_library(),
+				])
+			}
+			Message::ToggleOverview => {
+				if let AppState::Viewer {
+					show_overview,
+					thumbnails_scrolled,
+					..
+				} = &mut self.state
+				{
+					*show_overview = !*show_overview;
+					// The filmstrip and the overview start out unscrolled.
+					*thumbnails_scrolled = 0.0;
+					if *show_overview {
+						return self.request_thumbnails();
+					}
+				}
+				Command::none()
+			}
+			Message::ToggleSlideshow => {
+				let interval = self.slideshow_interval();
+				if let AppState::Viewer { slideshow, .. } = &mut self.state {
+					*slideshow = match slideshow {
+						Some(_) => None,
+						None => Some(Slideshow {
+							remaining: interval,
+							last_tick: Some(Instant::now()),
+						}),
+					};
+				}
+				Command::none()
+			}
+			Message::SpreadsFound(book, split_spreads, Ok(())) => {
+				let cur = match &self.state {
+					AppState::Viewer {
+						book: current_book,
+						cur,
+						..
+					} if Arc::ptr_eq(current_book, &book) => Some(
+						book.read()
+							.unwrap()
+							.page_in_layout(*cur, split_spreads),
+					),
+					_ => None,
+				};
+				book.write().unwrap().set_split_spreads(split_spreads);
+				self.library.mark_edited();
+				self.forget_bookmark_thumbnails(&book);
+				match cur {
+					// The pages are counted differently, so load them anew.
+					Some(cur) => self.open_book_viewer(book, cur),
+					None => Command::none(),
+				}
+			}
+			Message::SpreadsFound(_book, _split_spreads, Err(e)) => {
+				eprintln!("Unable to find the double pages: {e}");
+				Command::none()
+			}
+			Message::ToggleSplitSpreads => match &self.state {
+				AppState::Viewer { book, .. } => {
+					let split_spreads =
+						!book.read().unwrap().get_split_spreads();
+					self.update(Message::BookSplitSpreadsToggled(
+						Arc::clone(book),
+						split_spreads,
+					))
+				}
+				_ => Command::none(),
+			},
+			Message::WatchedFolderChanged => {
+				self.folders_changed_at = Some(Instant::now());
+				Command::none()
+			}
+			Message::WatchTimerTick(now) => match self.folders_changed_at {
+				Some(changed_at)
+					if now.duration_since(changed_at)
+						>= FOLDER_SCAN_DEBOUNCE =>
+				{
+					self.folders_changed_at = None;
+					self.scan_watched_folders()
+				}
+				_ => Command::none(),
+			},
+			Message::WheelScrolled(delta) => {
+				// The page scrolls first, and only turns once at its edge.
+				if std::mem::take(&mut self.page_scrolled) {
+					self.scroll_distance = Vector::new(0.0, 0.0);
+					return Command::none();
+				}
+				if !matches!(
+					self.state,
+					AppState::Viewer {
+						show_overview: false,
+						..
+					}
+				) {
+					return Command::none();
+				}
+				let go_forward = match delta {
+					mouse::ScrollDelta::Lines { x, y } => {
+						if x.abs() > y.abs() {
+							x < 0.0
+						} else {
+							y < 0.0
+						}
+					}
+					mouse::ScrollDelta::Pixels { x, y } => {
+						self.scroll_distance =
+							self.scroll_distance + Vector::new(x, y);
+						let Vector { x, y } = self.scroll_distance;
+						if x.abs().max(y.abs()) < SWIPE_DISTANCE {
+							return Command::none();
+						}
+						// Every swipe turns a single page.
+						self.scroll_distance = Vector::new(0.0, 0.0);
+						if x.abs() > y.abs() {
+							x < 0.0
+						} else {
+							y < 0.0
+						}
+					}
+				};
+				self.update(Message::AdvancePage(go_forward))
+			}
 			Message::WindowResized { height, width } => {
+				if (width, height) != (self.win_width, self.win_height) {
+					self.resized_at = Some(Instant::now());
+				}
 				self.win_height = height;
 				self.win_width = width;
 				Command::none()
 			}
+			Message::ZoneClicked(zone) => {
+				self.handle_input(Input::Mouse(zone), self.modifiers)
+			}
+			Message::Zoom(zoom_in) => {
+				if let AppState::Viewer { zoom, .. } = &mut self.state {
+					*zoom = if zoom_in {
+						(*zoom * ZOOM_STEP).min(MAX_ZOOM)
+					} else {
+						(*zoom / ZOOM_STEP).max(1.0)
+					};
+				}
+				self.rerender_pages()
+			}
 		}
 	}
 
 	fn subscription(&self) -> Subscription<Self::Message> {
-		subscription::events_with(|event, status| match (event, status) {
-			(
-				Event::Window(window::Event::Resized { width, height }),
-				event::Status::Ignored,
-			) => Some(Message::WindowResized { height, width }),
-			(
-				Event::Keyboard(keyboard::Event::KeyPressed {
-					key_code, ..
-				}),
-				event::Status::Ignored,
-			) => match key_code {
-				keyboard::KeyCode::Left => Some(Message::AdvancePage(false)),
-				keyboard::KeyCode::Right => Some(Message::AdvancePage(true)),
-				keyboard::KeyCode::Escape => Some(Message::GoBack),
+		let controls_timer = if self.controls_shown_at.is_some() {
+			time::every(Duration::from_millis(250))
+				.map(Message::ControlsTimerTick)
+		} else {
+			Subscription::none()
+		};
+		let events =
+			subscription::events_with(|event, status| match (event, status) {
+				(
+					Event::Window(window::Event::Resized { width, height }),
+					event::Status::Ignored,
+				) => Some(Message::WindowResized { height, width }),
+				(Event::Window(window::Event::CloseRequested), _) => {
+					Some(Message::CloseRequested)
+				}
+				(Event::Window(window::Event::FileDropped(path)), _) => {
+					Some(Message::FileDropped(path))
+				}
+				(
+					Event::Keyboard(keyboard::Event::KeyPressed {
+						key_code,
+						modifiers,
+					}),
+					event::Status::Ignored,
+				) => Some(Message::KeyPressed(key_code, modifiers)),
+				(
+					Event::Keyboard(keyboard::Event::ModifiersChanged(
+						modifiers,
+					)),
+					_,
+				) => Some(Message::ModifiersChanged(modifiers)),
+				(Event::Mouse(mouse::Event::CursorMoved { position }), _) => {
+					Some(Message::CursorMoved(position.x))
+				}
+				(Event::Mouse(mouse::Event::ButtonPressed(button)), _) => {
+					Some(Message::MouseButtonPressed(button))
+				}
+				(Event::Mouse(mouse::Event::WheelScrolled { delta }), _) => {
+					Some(Message::WheelScrolled(delta))
+				}
 				_ => None,
-			},
-			_ => None,
-		})
+			});
+		let resize_timer = if self.resized_at.is_some() {
+			time::every(Duration::from_millis(100))
+				.map(Message::ResizeTimerTick)
+		} else {
+			Subscription::none()
+		};
+		let preview_timer = if self.adjusted_at.is_some() {
+			time::every(Duration::from_millis(50))
+				.map(Message::PreviewTimerTick)
+		} else {
+			Subscription::none()
+		};
+		let slideshow_timer = match &self.state {
+			AppState::Viewer {
+				slideshow: Some(Slideshow {
+					last_tick: Some(_), ..
+				}),
+				..
+			} => time::every(Duration::from_millis(200))
+				.map(Message::SlideshowTick),
+			_ => Subscription::none(),
+		};
+		let autosave_timer = if self.library.is_dirty() {
+			time::every(Duration::from_millis(500))
+				.map(Message::AutosaveTimerTick)
+		} else {
+			Subscription::none()
+		};
+		let watch_timer = if self.folders_changed_at.is_some() {
+			time::every(Duration::from_millis(500)).map(Message::WatchTimerTick)
+		} else {
+			Subscription::none()
+		};
+		let folders = self.library.get_watched_folders();
+		let watcher = if folders.is_empty() {
+			Subscription::none()
+		} else {
+			watch_folders(folders.to_vec())
+				.map(|()| Message::WatchedFolderChanged)
+		};
+		Subscription::batch([
+			events,
+			controls_timer,
+			resize_timer,
+			preview_timer,
+			slideshow_timer,
+			autosave_timer,
+			watch_timer,
+			watcher,
+		])
 	}
 
 	fn view(&self) -> Element<'_, Self::Message, Renderer<Self::Theme>> {
@@ -345,16 +1997,564 @@ impl Application for App {
 				self.book_details_view(Arc::clone(book)).into()
 			}
 			AppState::Errored(e) => Self::errored_view(e).into(),
+			AppState::FolderImport { progress } => {
+				Self::folder_import_view(progress).into()
+			}
+			AppState::History { stats } => self.history_view(stats).into(),
+			AppState::ImportPreview { files } => {
+				Self::import_preview_view(files).into()
+			}
 			AppState::Library => self.library_view().into(),
 			AppState::Loading => Self::loading_view().into(),
-			AppState::Viewer { cur, images, .. } => {
-				let img = images.get(*cur);
-				self.viewer_view(img).into()
+			AppState::RestoreBackup { error, backups } => {
+				Self::restore_backup_view(error, backups).into()
+			}
+			AppState::Settings { capturing } => {
+				self.settings_view(*capturing).into()
+			}
+			AppState::Viewer {
+				book,
+				cur,
+				images,
+				page_input,
+				thumbnails,
+				show_overview,
+				editing_adjustments,
+				..
+			} => {
+				if *show_overview {
+					self.overview_view(*cur, thumbnails).into()
+				} else {
+					self.viewer_view(
+						book,
+						*cur,
+						images,
+						page_input,
+						thumbnails,
+						editing_adjustments.is_some(),
+					)
+					.into()
+				}
 			}
 		}
 	}
 }
 
+/// Draws the rating as filled and empty stars.
+fn rating_stars(rating: u8) -> String {
+	(1..=MAX_RATING)
+		.map(|star| if star <= rating { '★' } else { '☆' })
+		.collect()
+}
+
+fn is_modifier_key(key_code: keyboard::KeyCode) -> bool {
+	use keyboard::KeyCode;
+	matches!(
+		key_code,
+		KeyCode::LShift
+			| KeyCode::RShift
+			| KeyCode::LControl
+			| KeyCode::RControl
+			| KeyCode::LAlt
+			| KeyCode::RAlt
+			| KeyCode::LWin
+			| KeyCode::RWin
+	)
+}
+
+fn action_message(action: Action) -> Message {
+	match action {
+		Action::NextPage => Message::AdvancePage(true),
+		Action::PreviousPage => Message::AdvancePage(false),
+		Action::FirstPage => Message::GoToPage(0),
+		Action::LastPage => Message::GoToLastPage,
+		Action::SkipForward => Message::SkipPages(true),
+		Action::SkipBackward => Message::SkipPages(false),
+		Action::CycleFitMode => Message::CycleFitMode,
+		Action::FitBest => Message::FitModeSelected(FitMode::Best),
+		Action::FitWidth => Message::FitModeSelected(FitMode::Width),
+		Action::FitHeight => Message::FitModeSelected(FitMode::Height),
+		Action::FitOriginal => Message::FitModeSelected(FitMode::Original),
+		Action::FitFill => Message::FitModeSelected(FitMode::Fill),
+		Action::ToggleBookmark => Message::ToggleBookmark,
+		Action::ToggleFilmstrip => Message::ToggleFilmstrip,
+		Action::ToggleOverview => Message::ToggleOverview,
+		Action::ToggleFullscreen => Message::ToggleFullscreen,
+		Action::ToggleAutoCrop => Message::ToggleAutoCrop,
+		Action::ToggleAdjustments => Message::ToggleAdjustments,
+		Action::RotatePage => Message::RotatePage,
+		Action::RotateBook => Message::RotateBook,
+		Action::ToggleSlideshow => Message::ToggleSlideshow,
+		Action::ToggleSplitSpreads => Message::ToggleSplitSpreads,
+		Action::ZoomIn => Message::Zoom(true),
+		Action::ZoomOut => Message::Zoom(false),
+		Action::ResetZoom => Message::ResetZoom,
+		Action::Back => Message::GoBack,
+	}
+}
+
+impl App {
+	/// Binds the input when capturing in the settings, otherwise performs the
+	/// action bound to it.
+	fn handle_input(
+		&mut self,
+		input: Input,
+		modifiers: keyboard::Modifiers,
+	) -> Command<Message> {
+		if let AppState::Settings {
+			capturing: capturing @ Some(_),
+		} = &mut self.state
+		{
+			let action = capturing.take().expect("Should be capturing");
+			if input != Input::Key(keyboard::KeyCode::Escape) {
+				self.keymap
+					.add_binding(action, KeyBinding::new(input, modifiers));
+			}
+			return Command::none();
+		}
+		// While a slideshow runs, Space pauses it instead of turning the page.
+		if input == Input::Key(keyboard::KeyCode::Space)
+			&& modifiers.is_empty()
+			&& matches!(
+				self.state,
+				AppState::Viewer {
+					slideshow: Some(_),
+					..
+				}
+			) {
+			return self.update(Message::PauseSlideshow);
+		}
+		match self.keymap.action_for(input, modifiers) {
+			Some(action) => self.update(action_message(action)),
+			None => Command::none(),
+		}
+	}
+
+	fn open_book_details(&mut self, book: BookRef) -> Command<Message> {
+		let (id, pages) = {
+			let book = book.read().unwrap();
+			let pages = book
+				.get_bookmarks()
+				.iter()
+				.map(|b| (b.get_page(), book.get_page_rotation(b.get_page())))
+				.collect::<Vec<(usize, Rotation)>>();
+			(book.get_id(), pages)
+		};
+		self.state = AppState::BookDetails {
+			book: Arc::clone(&book),
+		};
+
+		let commands = pages
+			.into_iter()
+			.filter(|(page, _)| {
+				!self.bookmark_thumbnails.contains_key(&(id, *page))
+			})
+			.map(|(page, rotation)| {
+				let book = Arc::clone(&book);
+				Command::perform(
+					load_page_thumbnail(Arc::clone(&book), page, rotation),
+					move |res| {
+						Message::BookmarkThumbnailLoaded(book, page, res)
+					},
+				)
+			});
+		Command::batch(commands)
+	}
+
+	fn open_book_viewer(
+		&mut self,
+		book: BookRef,
+		page: usize,
+	) -> Command<Message> {
+		self.state = AppState::Viewer {
+			book: Arc::clone(&book),
+			cur: page,
+			images: Vec::new(),
+			page_input: String::new(),
+			thumbnails: Vec::new(),
+			requested_thumbnails: HashSet::new(),
+			thumbnails_scrolled: 0.0,
+			show_overview: false,
+			editing_adjustments: None,
+			slideshow: None,
+			zoom: 1.0,
+			scaled: HashSet::new(),
+			processed: HashMap::new(),
+		};
+		let load =
+			Command::perform(load_images(Arc::clone(&book)), move |res| {
+				Message::BookImagesLoaded(book, res)
+			});
+		if self.library.is_fullscreen() {
+			return Command::batch([
+				window::change_mode(window::Mode::Fullscreen),
+				load,
+			]);
+		}
+		load
+	}
+
+	/// The width the page gets in the viewer, between the page turning
+	/// buttons unless reading in fullscreen.
+	fn page_area_width(&self) -> f32 {
+		let width = self.win_width as f32;
+		if self.is_reading_fullscreen() {
+			width
+		} else {
+			// The padding of the viewer, and the 8 out of 10 portions of the
+			// row that the page gets.
+			(width - 40.0).max(0.0) * 0.8
+		}
+	}
+
+	/// The height the page gets in the viewer, above the controls and the
+	/// panels that are open unless reading in fullscreen.
+	fn page_area_height(&self) -> f32 {
+		let height = self.win_height as f32;
+		if self.is_reading_fullscreen() {
+			return height;
+		}
+		let mut controls = CONTROLS_ROW_HEIGHT;
+		if let AppState::Viewer {
+			images,
+			editing_adjustments,
+			..
+		} = &self.state
+		{
+			if images.len() > 1 {
+				controls += PAGE_SLIDER_HEIGHT + CONTROLS_SPACING;
+			}
+			if editing_adjustments.is_some() {
+				controls += ADJUSTMENTS_HEIGHT + CONTROLS_SPACING;
+			}
+		}
+		if self.show_filmstrip {
+			controls += FILMSTRIP_HEIGHT + CONTROLS_SPACING;
+		}
+		// The padding of the viewer, and the spacing above the controls.
+		(height - 60.0 - controls).max(0.0)
+	}
+
+	fn is_reading_fullscreen(&self) -> bool {
+		self.library.is_fullscreen()
+			&& matches!(self.state, AppState::Viewer { .. })
+	}
+
+	/// Restores the window when leaving the viewer, keeping the preference
+	/// for the next book.
+	fn leave_fullscreen(&mut self) -> Command<Message> {
+		self.controls_shown_at = None;
+		if self.library.is_fullscreen() {
+			window::change_mode(window::Mode::Windowed)
+		} else {
+			Command::none()
+		}
+	}
+
+	/// Moves the viewer to the given page, clamped to the pages in the book,
+	/// and logs the page turn in the reading history.
+	fn go_to_page(&mut self, index: usize) -> Command<Message> {
+		let interval = self.slideshow_interval();
+		let AppState::Viewer {
+			book,
+			cur,
+			images,
+			show_overview,
+			slideshow,
+			thumbnails_scrolled,
+			..
+		} = &mut self.state
+		else {
+			return Command::none();
+		};
+		if images.is_empty() {
+			return Command::none();
+		}
+		let previous = *cur;
+		*cur = index.min(images.len() - 1);
+		if std::mem::take(show_overview) {
+			*thumbnails_scrolled = 0.0;
+		}
+		if let Some(slideshow) = slideshow {
+			slideshow.remaining = interval;
+		}
+		let turn = (*cur != previous).then(|| {
+			PageTurn::now(book.read().unwrap().get_id(), *cur, images.len())
+		});
+		let mut commands = vec![
+			scrollable::snap_to(
+				page_scrollable_id(),
+				scrollable::RelativeOffset::START,
+			),
+			self.scale_pages(),
+		];
+		if let Some(turn) = turn {
+			self.history.push(turn);
+			commands.push(Command::perform(
+				log_page_turn(self.history_file.clone(), turn),
+				Message::PageTurnLogged,
+			));
+		}
+		Command::batch(commands)
+	}
+
+	/// Imports the books in the folder and its subfolders, showing the
+	/// progress.
+	fn import_folder(&mut self, folder: PathBuf) -> Command<Message> {
+		self.state = AppState::FolderImport {
+			progress: ImportProgress {
+				folder: folder.clone(),
+				found: None,
+				skipped: 0,
+				imported: 0,
+				failures: Vec::new(),
+			},
+		};
+		self.folder_imports += 1;
+		let import = self.folder_imports;
+		Command::perform(scan_folders(vec![folder]), move |found| {
+			Message::FolderScanned(import, found)
+		})
+	}
+
+	/// Selects the books shown between the selection anchor and the book,
+	/// or just the book if there is no anchor.
+	fn select_range(&mut self, id: Uuid) {
+		let ids = self
+			.shown_books()
+			.iter()
+			.map(|book| book.read().unwrap().get_id())
+			.collect::<Vec<Uuid>>();
+		let end = ids.iter().position(|b| *b == id);
+		let start = self
+			.selection_anchor
+			.and_then(|anchor| ids.iter().position(|b| *b == anchor))
+			.or(end);
+		if let (Some(start), Some(end)) = (start, end) {
+			let range = start.min(end)..=start.max(end);
+			self.selected_books.extend(ids[range].iter().copied());
+		}
+	}
+
+	/// The books shown in the library, with the collection, search and
+	/// filters applied.
+	fn shown_books(&self) -> Vec<BookRef> {
+		// An unfinished search shouldn't hide every book while typing.
+		let query = Query::parse(&self.library_query).ok();
+		self.library.filtered_books(
+			self.library_collection,
+			&self.library_filter,
+			query.as_ref(),
+			self.library_sort,
+		)
+	}
+
+	fn slideshow_interval(&self) -> Duration {
+		Duration::from_secs(self.library.get_slideshow_interval().into())
+	}
+
+	/// How the pages of the book are rendered in the current window.
+	fn page_options(&self, book: &BookRef) -> PageOptions {
+		// Zoomed in pages are scaled to their zoomed size, to stay sharp.
+		let zoom = match &self.state {
+			AppState::Viewer { zoom, .. } => *zoom,
+			_ => 1.0,
+		};
+		self.library.page_options_for(
+			&book.read().unwrap(),
+			(self.page_area_width() * zoom) as u32,
+			(self.page_area_height() * zoom) as u32,
+		)
+	}
+
+	/// Saves the library in the background, counting its changes as saved
+	/// unless that fails.
+	fn save_library(&mut self) -> Command<Message> {
+		if !self.library_loaded {
+			return Command::none();
+		}
+		self.edited_at = None;
+		if self.saving {
+			// Two saves at once could finish in either order, so this one
+			// waits, unless the window is already waiting to close.
+			self.queued_save.get_or_insert(Message::SaveLibrary);
+			return Command::none();
+		}
+		self.saving = true;
+		self.library.set_dirty(false);
+		Command::perform(
+			self.library.clone().save(self.library_file.clone()),
+			Message::SaveLibraryComplete,
+		)
+	}
+
+	/// Runs the save that waited for the last one to finish, if any.
+	fn run_queued_save(&mut self) -> Command<Message> {
+		self.saving = false;
+		match self.queued_save.take() {
+			Some(message) => self.update(message),
+			None => Command::none(),
+		}
+	}
+
+	/// Looks for new books in the watched folders, and for books whose files
+	/// are gone.
+	fn scan_watched_folders(&self) -> Command<Message> {
+		Command::perform(
+			scan_folders(self.library.get_watched_folders().to_vec()),
+			Message::BooksFound,
+		)
+	}
+
+	/// Processes the pages around the current one anew, for changes that
+	/// affect them all, such as a new window size. The pages processed
+	/// before are shown until the new ones are done.
+	fn rerender_pages(&mut self) -> Command<Message> {
+		if let AppState::Viewer { scaled, .. } = &mut self.state {
+			scaled.clear();
+		}
+		self.page_renders += 1;
+		self.scale_pages()
+	}
+
+	/// Rotates, crops, filters and scales the current page and the ones
+	/// around it for the window, unless they already are.
+	///
+	/// Decoding every page of a book takes long and a lot of memory, so it
+	/// is only done for the pages that are about to be read.
+	fn scale_pages(&mut self) -> Command<Message> {
+		let options = match &self.state {
+			AppState::Viewer { book, .. } => self.page_options(book),
+			_ => return Command::none(),
+		};
+		let renders = self.page_renders;
+		let AppState::Viewer {
+			book,
+			cur,
+			images,
+			scaled,
+			processed,
+			..
+		} = &mut self.state
+		else {
+			return Command::none();
+		};
+		if images.is_empty() {
+			return Command::none();
+		}
+		let end =
+			(*cur + SCALED_PAGES_AHEAD).min(images.len().saturating_sub(1));
+		let pages = cur.saturating_sub(1)..=end;
+		// Only the pages around the current one are kept processed.
+		scaled.retain(|index| pages.contains(index));
+		processed.retain(|index, _| pages.contains(index));
+		let commands = pages
+			.filter(|index| scaled.insert(*index))
+			.map(|index| {
+				let book = Arc::clone(book);
+				Command::perform(
+					load_page(Arc::clone(&book), index, options),
+					move |res| Message::PageReloaded(book, index, renders, res),
+				)
+			})
+			.collect::<Vec<_>>();
+		Command::batch(commands)
+	}
+
+	/// Processes the current page of the viewer anew with the latest
+	/// settings. The pages around it are processed again once the page is
+	/// turned.
+	fn preview_page(&mut self) -> Command<Message> {
+		let options = match &self.state {
+			AppState::Viewer { book, .. } => self.page_options(book),
+			_ => return Command::none(),
+		};
+		self.page_renders += 1;
+		let renders = self.page_renders;
+		let AppState::Viewer {
+			book, cur, scaled, ..
+		} = &mut self.state
+		else {
+			return Command::none();
+		};
+		scaled.clear();
+		scaled.insert(*cur);
+		let (book, cur) = (Arc::clone(book), *cur);
+		Command::perform(
+			load_page(Arc::clone(&book), cur, options),
+			move |res| Message::PageReloaded(book, cur, renders, res),
+		)
+	}
+
+	/// Drops the cached bookmark thumbnails of the book, for when its pages
+	/// are laid out differently.
+	fn forget_bookmark_thumbnails(&mut self, book: &BookRef) {
+		let book_id = book.read().unwrap().get_id();
+		self.bookmark_thumbnails.retain(|(id, _), _| *id != book_id);
+	}
+
+	/// The pages whose thumbnails are in view in the filmstrip or the
+	/// overview, or close to it.
+	fn shown_thumbnails(&self) -> Range<usize> {
+		let AppState::Viewer {
+			images,
+			show_overview,
+			thumbnails_scrolled,
+			..
+		} = &self.state
+		else {
+			return 0..0;
+		};
+		let (columns, rows) = if *show_overview {
+			(
+				(self.win_width / OVERVIEW_THUMBNAIL_WIDTH as u32).max(1),
+				(self.win_height as f32 / OVERVIEW_ROW_HEIGHT).ceil() as u32,
+			)
+		} else {
+			(
+				1,
+				(self.win_width as f32 / FILMSTRIP_THUMBNAIL_WIDTH).ceil()
+					as u32,
+			)
+		};
+		let (columns, shown) = (columns as usize, (columns * rows) as usize);
+		let first = (thumbnails_scrolled
+			* images.len().saturating_sub(shown) as f32) as usize;
+		let first = first / columns * columns;
+		first.saturating_sub(THUMBNAILS_AHEAD)
+			..(first + shown + THUMBNAILS_AHEAD).min(images.len())
+	}
+
+	/// Loads the thumbnails in view that haven't been requested yet.
+	fn request_thumbnails(&mut self) -> Command<Message> {
+		let shown = self.shown_thumbnails();
+		let AppState::Viewer {
+			book,
+			images,
+			thumbnails,
+			requested_thumbnails,
+			..
+		} = &mut self.state
+		else {
+			return Command::none();
+		};
+		if thumbnails.is_empty() {
+			*thumbnails = vec![None; images.len()];
+		}
+		let pages = shown
+			.filter(|index| requested_thumbnails.insert(*index))
+			.collect::<Vec<usize>>();
+		if pages.is_empty() {
+			return Command::none();
+		}
+		let book = Arc::clone(book);
+		Command::perform(
+			load_page_thumbnails(Arc::clone(&book), pages),
+			move |res| Message::ThumbnailsLoaded(book, res),
+		)
+	}
+}
+
 impl<'a> App {
 	fn container(title: &str) -> Column<'a, Message> {
 		column![text(title).size(50)].spacing(20).padding(20)
@@ -388,15 +2588,83 @@ impl<'a> App {
 		}
 
 		let label_size = 100;
-		let (author, path, title) = {
+		let (author, mut path, series, title) = {
 			let book = book.read().unwrap();
 			(
 				book.get_author().to_string(),
 				book.get_path_str().to_string(),
+				book.get_series().to_string(),
 				book.get_title().to_string(),
 			)
 		};
+		if book.read().unwrap().is_missing() {
+			path.push_str(" (file not found)");
+		}
+		let release = {
+			let book = book.read().unwrap();
+			let parts = [
+				book.get_volume().map(|volume| format!("Volume {volume}")),
+				book.get_chapter()
+					.map(|chapter| format!("Chapter {chapter}")),
+				book.get_year().map(|year| year.to_string()),
+				book.get_scan_group().map(|group| format!("by {group}")),
+			];
+			let parts = parts.into_iter().flatten().collect::<Vec<String>>();
+			if parts.is_empty() {
+				"Unknown".to_owned()
+			} else {
+				parts.join(", ")
+			}
+		};
+		let (reading_direction, rotation, split_spreads) = {
+			let book = book.read().unwrap();
+			(
+				book.get_reading_direction(),
+				book.get_rotation(),
+				book.get_split_spreads(),
+			)
+		};
+		let (rating, favorite, status) = {
+			let book = book.read().unwrap();
+			(book.get_rating(), book.is_favorite(), book.get_status())
+		};
+		let mut stars = row![].spacing(4).align_items(Alignment::Center);
+		for star in 1..=MAX_RATING {
+			// Clicking the current rating again clears it.
+			let new_rating = if star == rating { 0 } else { star };
+			stars = stars.push(
+				button(if star <= rating { "★" } else { "☆" })
+					.on_press(Message::BookRatingChanged(
+						Arc::clone(&book),
+						new_rating,
+					))
+					.style(theme::Button::Text),
+			);
+		}
+		let book_id = book.read().unwrap().get_id();
+		let mut collections =
+			row![text("Collections").width(label_size)].spacing(20);
+		if self.library.get_collections().is_empty() {
+			collections = collections.push(text("No collections yet"));
+		}
+		for collection in self.library.get_collections() {
+			let c_book = Arc::clone(&book);
+			let id = collection.get_id();
+			collections = collections.push(checkbox(
+				collection.get_name(),
+				collection.contains(&book_id),
+				move |added| {
+					Message::BookCollectionToggled(c_book.clone(), id, added)
+				},
+			));
+		}
 		let a_book = Arc::clone(&book);
+		let d_book = Arc::clone(&book);
+		let f_book = Arc::clone(&book);
+		let r_book = Arc::clone(&book);
+		let s_book = Arc::clone(&book);
+		let se_book = Arc::clone(&book);
+		let st_book = Arc::clone(&book);
 		let t_book = Arc::clone(&book);
 		row![
 			scrollable(book_list).width(250),
@@ -432,6 +2700,93 @@ impl<'a> App {
 							]
 							.spacing(20)
 							.align_items(Alignment::Center),
+							row![
+								text("Series").width(label_size),
+								text_input("Enter a series...", &series)
+									.on_input(move |series| {
+										let book = se_book.clone();
+										Message::BookSeriesChanged(book, series)
+									})
+							]
+							.spacing(20)
+							.align_items(Alignment::Center),
+							row![
+								text("Release").width(label_size),
+								text(release)
+							]
+							.spacing(20)
+							.align_items(Alignment::Center),
+							row![
+								text("Direction").width(label_size),
+								pick_list(
+									&ReadingDirection::ALL[..],
+									Some(reading_direction),
+									move |direction| {
+										let book = d_book.clone();
+										Message::BookReadingDirectionChanged(
+											book, direction,
+										)
+									}
+								),
+								checkbox(
+									"Split double pages",
+									split_spreads,
+									move |split_spreads| {
+										let book = s_book.clone();
+										Message::BookSplitSpreadsToggled(
+											book,
+											split_spreads,
+										)
+									}
+								)
+							]
+							.spacing(20)
+							.align_items(Alignment::Center),
+							row![
+								text("Rating").width(label_size),
+								stars,
+								checkbox(
+									"Favorite",
+									favorite,
+									move |favorite| {
+										let book = f_book.clone();
+										Message::BookFavoriteToggled(
+											book, favorite,
+										)
+									}
+								),
+							]
+							.spacing(20)
+							.align_items(Alignment::Center),
+							row![
+								text("Status").width(label_size),
+								pick_list(
+									&ReadStatus::ALL[..],
+									Some(status),
+									move |status| {
+										let book = st_book.clone();
+										Message::BookStatusChanged(book, status)
+									}
+								),
+							]
+							.spacing(20)
+							.align_items(Alignment::Center),
+							collections.align_items(Alignment::Center),
+							row![
+								text("Rotation").width(label_size),
+								pick_list(
+									&Rotation::ALL[..],
+									Some(rotation),
+									move |rotation| {
+										let book = r_book.clone();
+										Message::BookRotationChanged(
+											book, rotation,
+										)
+									}
+								),
+							]
+							.spacing(20)
+							.align_items(Alignment::Center),
 							row![text("Path").width(label_size), text(path)]
 								.spacing(20)
 								.align_items(Alignment::Center)
@@ -440,41 +2795,548 @@ impl<'a> App {
 					]
 					.spacing(20),
 				)
-				.push(vertical_space(Length::Fill))
-				.push(button("Back").on_press(Message::GoBack))
-				.width(Length::Fill)
+				.push(self.bookmarks_view(&book))
+				.push(button("Back").on_press(Message::GoBack))
+				.width(Length::Fill)
+		]
+	}
+
+	fn bookmarks_view(&self, book: &BookRef) -> Element<'a, Message> {
+		let (id, bookmarks) = {
+			let book = book.read().unwrap();
+			(book.get_id(), book.get_bookmarks().clone())
+		};
+		if bookmarks.is_empty() {
+			return vertical_space(Length::Fill).into();
+		}
+
+		let mut list = column![].spacing(10).padding([0, 20, 0, 0]);
+		for bookmark in bookmarks {
+			let page = bookmark.get_page();
+			let img = self
+				.bookmark_thumbnails
+				.get(&(id, page))
+				.map(|i| image(i.clone()))
+				.unwrap_or_else(|| {
+					image(format!(
+						"{}/images/waiting.png",
+						env!("CARGO_MANIFEST_DIR")
+					))
+				});
+			let n_book = Arc::clone(book);
+			list = list.push(
+				row![
+					button(img.height(100))
+						.padding(0)
+						.on_press(Message::OpenBookmark(Arc::clone(book), page))
+						.style(theme::Button::Text),
+					column![
+						text(format!("Page {}", page + 1)),
+						text_input("Add a note...", bookmark.get_note())
+							.on_input(move |note| {
+								let book = Arc::clone(&n_book);
+								Message::BookmarkNoteChanged(book, page, note)
+							}),
+					]
+					.spacing(10)
+					.width(Length::Fill),
+					button("Remove").on_press(Message::RemoveBookmark(
+						Arc::clone(book),
+						page
+					)),
+				]
+				.spacing(20)
+				.align_items(Alignment::Center),
+			);
+		}
+
+		column![
+			text("Bookmarks").size(24),
+			scrollable(list).height(Length::Fill)
+		]
+		.spacing(10)
+		.height(Length::Fill)
+		.into()
+	}
+
+	fn loading_view() -> Column<'a, Message> {
+		Self::container("Loading").push("Loading")
+	}
+
+	fn history_view(&self, stats: &ReadingStats) -> Column<'a, Message> {
+		let label_size = 200;
+		let summary = column![
+			row![
+				text("Pages read").width(label_size),
+				text(stats.total_pages)
+			],
+			row![
+				text("Time spent reading").width(label_size),
+				text(format_duration(stats.total_time))
+			],
+			row![
+				text("Books finished").width(label_size),
+				text(stats.books_finished)
+			],
+			row![
+				text("Current streak").width(label_size),
+				text(format!("{} days", stats.current_streak))
+			],
+			row![
+				text("Longest streak").width(label_size),
+				text(format!("{} days", stats.longest_streak))
+			],
+		]
+		.spacing(10);
+
+		let mut days = column![row![
+			text("Day").width(label_size),
+			text("Books").width(100),
+			text("Pages").width(100),
+			text("Time").width(100),
+		]]
+		.spacing(10);
+		if stats.recent_days.is_empty() {
+			days = days.push(text("Nothing read in the last two weeks."));
+		}
+		for day in &stats.recent_days {
+			days = days.push(row![
+				text(day.date()).width(label_size),
+				text(day.books).width(100),
+				text(day.pages).width(100),
+				text(format_duration(day.time)).width(100),
+			]);
+		}
+
+		let ranking = |title: &str, entries: &[(String, usize)]| {
+			let mut col = column![text(title).size(24)].spacing(10);
+			if entries.is_empty() {
+				col = col.push(text("None yet"));
+			}
+			for (name, pages) in entries {
+				col = col.push(row![
+					text(name).width(label_size),
+					text(format!("{pages} pages"))
+				]);
+			}
+			col
+		};
+
+		Self::container("Reading history")
+			.push(
+				scrollable(
+					column![
+						summary,
+						text("Last two weeks").size(24),
+						days,
+						row![
+							ranking("Top authors", &stats.top_authors)
+								.width(Length::Fill),
+							ranking("Top tags", &stats.top_tags)
+								.width(Length::Fill),
+						]
+						.spacing(20),
+					]
+					.spacing(20),
+				)
+				.height(Length::Fill),
+			)
+			.push(button("Back").on_press(Message::GoBack))
+	}
+
+	/// The collections to pick from, starting with the whole library if
+	/// `include_all` is set. Smart collections can't have books added to
+	/// them, so they are only listed along with the whole library.
+	fn collection_choices(&self, include_all: bool) -> Vec<CollectionChoice> {
+		let all = include_all.then(|| CollectionChoice {
+			id: None,
+			name: "All books".to_owned(),
+		});
+		all.into_iter()
+			.chain(
+				self.library
+					.get_collections()
+					.iter()
+					.filter(|c| include_all || !c.is_smart())
+					.map(|c| CollectionChoice {
+						id: Some(c.get_id()),
+						name: c.get_name().to_owned(),
+					}),
+			)
+			.collect()
+	}
+
+	fn bulk_edit_view(&self, bulk_edit: &BulkEdit) -> Element<'a, Message> {
+		let (author, series, tags) =
+			(bulk_edit.clone(), bulk_edit.clone(), bulk_edit.clone());
+		let (direction, status) = (bulk_edit.clone(), bulk_edit.clone());
+
+		column![
+			text(format!(
+				"Edit {} books. Empty fields are left as they are.",
+				self.selected_books.len()
+			)),
+			row![
+				text_input("Author", &bulk_edit.author).on_input(move |a| {
+					Message::BulkEditChanged(BulkEdit {
+						author: a,
+						..author.clone()
+					})
+				}),
+				text_input("Series", &bulk_edit.series).on_input(move |s| {
+					Message::BulkEditChanged(BulkEdit {
+						series: s,
+						..series.clone()
+					})
+				}),
+				text_input("Tags, separated by commas", &bulk_edit.tags)
+					.on_input(move |t| {
+						Message::BulkEditChanged(BulkEdit {
+							tags: t,
+							..tags.clone()
+						})
+					}),
+			]
+			.spacing(10),
+			row![
+				pick_list(
+					&ReadingDirection::ALL[..],
+					bulk_edit.direction,
+					move |d| {
+						Message::BulkEditChanged(BulkEdit {
+							direction: Some(d),
+							..direction.clone()
+						})
+					}
+				)
+				.placeholder("Direction"),
+				pick_list(&ReadStatus::ALL[..], bulk_edit.status, move |s| {
+					Message::BulkEditChanged(BulkEdit {
+						status: Some(s),
+						..status.clone()
+					})
+				})
+				.placeholder("Status"),
+				horizontal_space(Length::Fill),
+				button("Cancel").on_press(Message::ToggleBulkEdit),
+				button("Apply").on_press(Message::ApplyBulkEdit),
+			]
+			.spacing(10)
+			.align_items(Alignment::Center),
 		]
+		.spacing(10)
+		.into()
 	}
 
-	fn loading_view() -> Column<'a, Message> {
-		Self::container("Loading").push("Loading")
+	fn folder_import_view(progress: &ImportProgress) -> Column<'a, Message> {
+		let mut container = Self::container("Import folder")
+			.push(text(progress.folder.display().to_string()));
+		let Some(found) = progress.found else {
+			return container.push(text("Looking for books..."));
+		};
+
+		let to_import = found - progress.skipped;
+		let done = progress.imported + progress.failures.len();
+		container = container
+			.push(text(format!(
+				"Found {found} books, {} of which were already in the library",
+				progress.skipped
+			)))
+			.push(progress_bar(0.0..=to_import.max(1) as f32, done as f32))
+			.push(text(format!(
+				"Imported {} of {to_import} books, {} failed",
+				progress.imported,
+				progress.failures.len()
+			)));
+
+		let mut failures = column![].spacing(10).padding([0, 20, 0, 0]);
+		for (path, e) in &progress.failures {
+			failures = failures.push(
+				row![
+					text(path.display().to_string()).width(Length::Fill),
+					text(e),
+				]
+				.spacing(20),
+			);
+		}
+		container
+			.push(scrollable(failures).height(Length::Fill))
+			.push(row![
+				horizontal_space(Length::Fill),
+				button(if progress.is_finished() {
+					"Done"
+				} else {
+					"Continue in the background"
+				})
+				.on_press(Message::GoBack),
+			])
+	}
+
+	fn import_preview_view(files: &[ImportCandidate]) -> Column<'a, Message> {
+		let cell = |value: Option<String>, width: u16| {
+			text(value.unwrap_or_default()).width(width)
+		};
+		let mut list = column![row![
+			horizontal_space(30),
+			text("File").width(Length::FillPortion(3)),
+			text("Title").width(Length::FillPortion(3)),
+			text("Series").width(Length::FillPortion(2)),
+			text("Volume").width(80),
+			text("Chapter").width(80),
+			text("Year").width(80),
+			text("Group").width(Length::FillPortion(1)),
+		]
+		.spacing(10)]
+		.spacing(10)
+		.padding([0, 20, 0, 0]);
+		for (index, file) in files.iter().enumerate() {
+			let name = file
+				.path
+				.file_name()
+				.map(|name| name.to_string_lossy().into_owned())
+				.unwrap_or_default();
+			let stem = file
+				.path
+				.file_stem()
+				.map(|stem| stem.to_string_lossy().into_owned())
+				.unwrap_or_default();
+			list = list.push(
+				row![
+					checkbox("", file.selected, move |selected| {
+						Message::ImportCandidateToggled(index, selected)
+					})
+					.width(30),
+					text(name).width(Length::FillPortion(3)),
+					text_input(&stem, &file.title)
+						.on_input(move |title| {
+							Message::ImportCandidateTitleChanged(index, title)
+						})
+						.width(Length::FillPortion(3)),
+					text_input(
+						"Series",
+						file.info.series.as_deref().unwrap_or_default()
+					)
+					.on_input(move |series| {
+						Message::ImportCandidateSeriesChanged(index, series)
+					})
+					.width(Length::FillPortion(2)),
+					cell(file.info.volume.map(|v| v.to_string()), 80),
+					cell(file.info.chapter.clone(), 80),
+					cell(file.info.year.map(|y| y.to_string()), 80),
+					text(file.info.group.clone().unwrap_or_default())
+						.width(Length::FillPortion(1)),
+				]
+				.spacing(10)
+				.align_items(Alignment::Center),
+			);
+		}
+
+		let count = files.iter().filter(|file| file.selected).count();
+		Self::container("Import books")
+			.push(text(
+				"These details were found in the file names. Correct the titles \
+				 and series, and untick the files you don't want to import.",
+			))
+			.push(scrollable(list).height(Length::Fill))
+			.push(
+				row![
+					button("Cancel").on_press(Message::GoBack),
+					horizontal_space(Length::Fill),
+					button(text(format!("Import {count} books")))
+						.on_press(Message::ConfirmImport),
+				]
+				.spacing(20),
+			)
 	}
 
 	fn library_view(&self) -> Column<'a, Message> {
 		const BOOK_WIDTH: u16 = 200;
 
+		let filter = self.library_filter;
+		let filters = row![
+			checkbox(
+				"Favorites",
+				filter.favorites_only,
+				move |favorites_only| {
+					Message::LibraryFilterChanged(BookFilter {
+						favorites_only,
+						..filter
+					})
+				}
+			),
+			pick_list(
+				&StatusFilter::ALL[..],
+				Some(filter.status),
+				move |status| {
+					Message::LibraryFilterChanged(BookFilter {
+						status,
+						..filter
+					})
+				}
+			),
+			pick_list(
+				(0..=MAX_RATING).collect::<Vec<u8>>(),
+				Some(filter.min_rating),
+				move |min_rating| {
+					Message::LibraryFilterChanged(BookFilter {
+						min_rating,
+						..filter
+					})
+				}
+			),
+			text("stars or more"),
+			horizontal_space(Length::Fill),
+			button("Select all").on_press(Message::SelectAllBooks),
+			text("Sort by"),
+			pick_list(
+				&SortKey::ALL[..],
+				Some(self.library_sort),
+				Message::LibrarySortChanged
+			),
+		]
+		.spacing(10)
+		.align_items(Alignment::Center);
+
+		let collection = self
+			.library_collection
+			.and_then(|id| self.library.get_collection(id));
+		let mut collections = row![
+			pick_list(
+				self.collection_choices(true),
+				Some(CollectionChoice {
+					id: collection.map(|c| c.get_id()),
+					name: collection
+						.map_or("All books", |c| c.get_name())
+						.to_owned(),
+				}),
+				Message::CollectionSelected
+			),
+			text_input("Collection name...", &self.collection_name)
+				.on_input(Message::CollectionNameChanged)
+				.on_submit(Message::CreateCollection)
+				.width(200),
+			button("New collection").on_press(Message::CreateCollection),
+		]
+		.spacing(10)
+		.align_items(Alignment::Center);
+		if collection.is_some() {
+			collections = collections
+				.push(button("Rename").on_press(Message::RenameCollection))
+				.push(
+					button("Delete collection")
+						.on_press(Message::DeleteCollection),
+				);
+		}
+		if !self.selected_books.is_empty() {
+			collections = collections
+				.push(horizontal_space(Length::Fill))
+				.push(text(format!("{} selected", self.selected_books.len())))
+				.push(
+					pick_list(
+						self.collection_choices(false),
+						None,
+						Message::AddSelectionToCollection,
+					)
+					.placeholder("Add to collection..."),
+				)
+				.push(button("Edit selected").on_press(Message::ToggleBulkEdit))
+				.push(
+					button("Clear selection").on_press(Message::ClearSelection),
+				);
+		}
+		let query = Query::parse(&self.library_query);
+		let mut search = row![text_input(
+			"Search, e.g. unread tag:shonen added:<30d",
+			&self.library_query
+		)
+		.on_input(Message::LibraryQueryChanged)
+		.width(400),]
+		.spacing(10)
+		.align_items(Alignment::Center);
+		match &query {
+			Ok(query) if !query.is_empty() => {
+				search = search.push(
+					button("Save as smart collection")
+						.on_press(Message::SaveSmartCollection),
+				);
+			}
+			Ok(_) => {}
+			Err(e) => search = search.push(text(e.to_string())),
+		}
+		if let Some(query) = collection.and_then(|c| c.get_query()) {
+			search = search
+				.push(horizontal_space(Length::Fill))
+				.push(text(format!("Smart collection: {query}")));
+		}
+
+		// Books can only be moved around in a collection's own order.
+		let can_reorder = collection.is_some_and(|c| !c.is_smart())
+			&& self.library_sort == SortKey::Added;
+
 		let mut col = column![].spacing(20).padding([0, 20, 0, 0]);
 		let chunk_size = (self.win_width / BOOK_WIDTH as u32).max(1) as usize;
-		for chunk in self.library.get_books().chunks(chunk_size) {
+		let books = self.shown_books();
+		for chunk in books.chunks(chunk_size) {
 			let mut row: Row<'a, Message> = row!().spacing(20);
 			for b in chunk {
-				let title = {
+				let (id, title, rating, favorite, status, missing) = {
 					let book = b.read().unwrap();
-					book.get_title().to_string()
+					(
+						book.get_id(),
+						book.get_title().to_string(),
+						book.get_rating(),
+						book.is_favorite(),
+						book.get_status(),
+						book.is_missing(),
+					)
 				};
-				let msg = Message::OpenBookDetails(Arc::clone(b));
-				row = row.push(
-					button(column![
-						container(self.get_image_for_book(b).width(BOOK_WIDTH))
-							.center_x()
-							.width(BOOK_WIDTH),
-						text(title).width(Length::Fill)
-					])
-					.padding(0)
-					.on_press(msg)
-					.style(theme::Button::Text)
-					.width(Length::Fill),
-				);
+				let mut badges = row![].spacing(10);
+				if missing {
+					badges = badges.push(text("Missing").size(14));
+				}
+				if favorite {
+					badges = badges.push(text("♥").size(14));
+				}
+				if rating > 0 {
+					badges = badges.push(text(rating_stars(rating)).size(14));
+				}
+				badges = badges.push(text(status.to_string()).size(14));
+				let msg = Message::BookTileClicked(Arc::clone(b));
+				let mut tile = column![button(column![
+					container(self.get_image_for_book(b).width(BOOK_WIDTH))
+						.center_x()
+						.width(BOOK_WIDTH),
+					text(title).width(Length::Fill),
+					badges,
+				])
+				.padding(0)
+				.on_press(msg)
+				.style(if self.selected_books.contains(&id) {
+					theme::Button::Secondary
+				} else {
+					theme::Button::Text
+				})
+				.width(Length::Fill)]
+				.spacing(6);
+				if can_reorder {
+					tile = tile.push(
+						row![
+							button("◀").on_press(Message::MoveInCollection(
+								Arc::clone(b),
+								false
+							)),
+							button("▶").on_press(Message::MoveInCollection(
+								Arc::clone(b),
+								true
+							)),
+							button("Remove").on_press(
+								Message::RemoveFromCollection(Arc::clone(b))
+							),
+						]
+						.spacing(6),
+					);
+				}
+				row = row.push(tile.width(Length::Fill));
 			}
 			for _ in chunk.len()..chunk_size {
 				row = row.push(horizontal_space(Length::Fill));
@@ -482,28 +3344,376 @@ impl<'a> App {
 			col = col.push(row);
 		}
 
-		Self::container("Library")
+		let mut container =
+			Self::container(collection.map_or("Library", |c| c.get_name()))
+				.push(collections);
+		if let Some(bulk_edit) = &self.bulk_edit {
+			container = container.push(self.bulk_edit_view(bulk_edit));
+		}
+		container
+			.push(search)
+			.push(filters)
 			.push(scrollable(col).height(Length::Fill))
 			.push(
 				row![
 					button("Add book").on_press(Message::ImportSingleBook),
 					button("Quick Import")
 						.on_press(Message::ImportMultipleBooks),
+					button("Import folder").on_press(Message::ImportFolder),
 					horizontal_space(Length::Fill),
+					button("History").on_press(Message::OpenHistory),
+					button("Settings").on_press(Message::OpenSettings),
 					button("Save").on_press(Message::SaveLibrary)
 				]
 				.spacing(20),
 			)
 	}
 
+	fn settings_view(&self, capturing: Option<Action>) -> Column<'a, Message> {
+		let mut list = column![].spacing(10).padding([0, 20, 0, 0]);
+		for action in Action::ALL {
+			let mut bindings =
+				row![].spacing(10).align_items(Alignment::Center);
+			for binding in self.keymap.get_bindings(action) {
+				bindings = bindings.push(
+					button(text(binding.to_string()))
+						.on_press(Message::RemoveBinding(action, *binding))
+						.style(theme::Button::Secondary),
+				);
+			}
+			if capturing == Some(action) {
+				bindings =
+					bindings.push(text("Press a key or mouse button, or"));
+				for zone in MouseInput::ZONES {
+					bindings = bindings.push(
+						button(text(zone.to_string()))
+							.on_press(Message::ZoneClicked(zone)),
+					);
+				}
+			} else {
+				bindings = bindings.push(
+					button("Add key").on_press(Message::CaptureBinding(action)),
+				);
+			}
+			list = list.push(
+				row![text(action.to_string()).width(200), bindings]
+					.spacing(20)
+					.align_items(Alignment::Center),
+			);
+		}
+
+		let auto_crop = self.library.get_auto_crop();
+		let pages = column![
+			text("Pages").size(24),
+			checkbox(
+				"Automatically crop page margins",
+				auto_crop.enabled,
+				Message::AutoCropToggled
+			),
+			row![
+				text("Crop tolerance").width(200),
+				slider(
+					0..=64,
+					auto_crop.tolerance,
+					Message::AutoCropToleranceChanged
+				)
+				.width(300),
+				text(auto_crop.tolerance),
+			]
+			.spacing(20)
+			.align_items(Alignment::Center),
+			row![
+				text("Slideshow interval").width(200),
+				slider(
+					1..=60,
+					self.library.get_slideshow_interval(),
+					Message::SlideshowIntervalChanged
+				)
+				.width(300),
+				text(format!("{}s", self.library.get_slideshow_interval())),
+			]
+			.spacing(20)
+			.align_items(Alignment::Center),
+			row![
+				text("Downscaling filter").width(200),
+				pick_list(
+					&ScalingFilter::ALL[..],
+					Some(self.library.get_scaling_filter()),
+					Message::ScalingFilterSelected
+				),
+			]
+			.spacing(20)
+			.align_items(Alignment::Center),
+		]
+		.spacing(10);
+
+		let mut folders = column![
+			text("Watched folders").size(24),
+			text("New books in these folders are imported automatically.")
+				.size(14),
+		]
+		.spacing(10);
+		for folder in self.library.get_watched_folders() {
+			folders = folders.push(
+				row![
+					text(folder.display().to_string()).width(Length::Fill),
+					button("Remove")
+						.on_press(Message::RemoveWatchedFolder(folder.clone()))
+						.style(theme::Button::Secondary),
+				]
+				.spacing(20)
+				.align_items(Alignment::Center),
+			);
+		}
+		folders = folders
+			.push(button("Add folder").on_press(Message::AddWatchedFolder));
+
+		Self::container("Settings")
+			.push(pages)
+			.push(folders)
+			.push(text("Key bindings").size(24))
+			.push(text("Click a key to remove it from an action.").size(14))
+			.push(scrollable(list).height(Length::Fill))
+			.push(
+				row![
+					button("Back").on_press(Message::GoBack),
+					horizontal_space(Length::Fill),
+					button("Reset to defaults").on_press(Message::ResetKeymap),
+					button("Save").on_press(Message::SaveSettings)
+				]
+				.spacing(20),
+			)
+	}
+
+	fn restore_backup_view(
+		error: &str,
+		backups: &[PathBuf],
+	) -> Column<'a, Message> {
+		let mut list = column![].spacing(10).padding([0, 20, 0, 0]);
+		for backup in backups {
+			let name = backup
+				.file_name()
+				.map(|name| name.to_string_lossy().into_owned())
+				.unwrap_or_default();
+			list = list.push(
+				row![
+					text(name).width(Length::Fill),
+					button("Restore")
+						.on_press(Message::RestoreBackup(backup.clone())),
+				]
+				.spacing(20)
+				.align_items(Alignment::Center),
+			);
+		}
+		Self::container("Restore library")
+			.push(text(format!("The library couldn't be loaded: {error}.")))
+			.push(text(
+				"Restore one of its backups, newest first. The damaged file \
+				 is kept next to the library with a .damaged extension.",
+			))
+			.push(scrollable(list).height(Length::Fill))
+	}
+
 	fn errored_view(e: &'a str) -> Column<'a, Message> {
 		Self::container("Error").push(e)
 	}
 
 	fn viewer_view(
 		&self,
-		img: Option<&'a image::Handle>,
+		book: &BookRef,
+		cur: usize,
+		images: &'a [image::Handle],
+		page_input: &str,
+		thumbnails: &'a [Option<image::Handle>],
+		editing_adjustments: bool,
 	) -> Column<'a, Message> {
+		let fit_mode = self.library.fit_mode_for(&book.read().unwrap());
+		let rotation = book.read().unwrap().get_page_rotation(cur);
+		let (slideshow, zoom, processed) = match &self.state {
+			AppState::Viewer {
+				slideshow,
+				zoom,
+				processed,
+				..
+			} => (*slideshow, *zoom, processed.get(&cur)),
+			_ => (None, 1.0, None),
+		};
+		let countdown = slideshow.map(|slideshow| {
+			if slideshow.last_tick.is_some() {
+				format!(
+					"Next page in {}s",
+					slideshow.remaining.as_secs_f32().ceil()
+				)
+			} else {
+				"Slideshow paused".to_owned()
+			}
+		});
+		let page = processed
+			.or(images.get(cur))
+			.map(|img| image(img.clone()))
+			.unwrap_or_else(|| {
+				image(format!(
+					"{}/images/waiting.png",
+					env!("CARGO_MANIFEST_DIR")
+				))
+			});
+		let page: Element<'a, Message> = match fit_mode {
+			// A zoomed in page is scrolled around at its zoomed width,
+			// whatever the fit mode.
+			_ if zoom > 1.0 => scrollable(
+				page.width(Length::Fixed(self.page_area_width() * zoom)),
+			)
+			.id(page_scrollable_id())
+			.on_scroll(|_| Message::PageScrolled)
+			.direction(scrollable::Direction::Both {
+				vertical: scrollable::Properties::default(),
+				horizontal: scrollable::Properties::default(),
+			})
+			.width(Length::FillPortion(8))
+			.height(Length::Fill)
+			.into(),
+			FitMode::Best => page.content_fit(ContentFit::ScaleDown).into(),
+			FitMode::Width => scrollable(page.width(Length::Fill))
+				.id(page_scrollable_id())
+				.on_scroll(|_| Message::PageScrolled)
+				.width(Length::FillPortion(8))
+				.height(Length::Fill)
+				.into(),
+			FitMode::Height => scrollable(page.height(Length::Fill))
+				.id(page_scrollable_id())
+				.on_scroll(|_| Message::PageScrolled)
+				.direction(scrollable::Direction::Horizontal(
+					scrollable::Properties::default(),
+				))
+				.width(Length::FillPortion(8))
+				.height(Length::Fill)
+				.into(),
+			FitMode::Original => scrollable(page.content_fit(ContentFit::None))
+				.id(page_scrollable_id())
+				.on_scroll(|_| Message::PageScrolled)
+				.direction(scrollable::Direction::Both {
+					vertical: scrollable::Properties::default(),
+					horizontal: scrollable::Properties::default(),
+				})
+				.width(Length::FillPortion(8))
+				.height(Length::Fill)
+				.into(),
+			FitMode::Fill => page
+				.content_fit(ContentFit::Fill)
+				.width(Length::FillPortion(8))
+				.height(Length::Fill)
+				.into(),
+		};
+
+		let (is_bookmarked, note) = {
+			let book = book.read().unwrap();
+			let note = book
+				.get_bookmarks()
+				.iter()
+				.find(|b| b.get_page() == cur)
+				.map(|b| b.get_note().to_string());
+			(note.is_some(), note.unwrap_or_default())
+		};
+
+		let mut controls = column![].spacing(10);
+		if editing_adjustments {
+			controls = controls.push(self.adjustments_view(book));
+		}
+		if self.show_filmstrip {
+			controls = controls.push(Self::filmstrip_view(cur, thumbnails));
+		}
+		if images.len() > 1 {
+			let last = (images.len() - 1) as u32;
+			controls = controls.push(slider(0..=last, cur as u32, |index| {
+				Message::GoToPage(index as usize)
+			}));
+		}
+		let mut bookmark_row = row![button(if is_bookmarked {
+			"Remove bookmark"
+		} else {
+			"Bookmark"
+		})
+		.on_press(Message::ToggleBookmark)]
+		.spacing(20)
+		.align_items(Alignment::Center);
+		if is_bookmarked {
+			let book = Arc::clone(book);
+			bookmark_row = bookmark_row.push(
+				text_input("Bookmark note...", &note)
+					.on_input(move |note| {
+						Message::BookmarkNoteChanged(
+							Arc::clone(&book),
+							cur,
+							note,
+						)
+					})
+					.width(300),
+			);
+		}
+		controls = controls.push(
+			row![
+				button("Back").on_press(Message::GoBack),
+				bookmark_row,
+				horizontal_space(Length::Fill),
+				text(countdown.clone().unwrap_or_default()),
+				text(if images.is_empty() {
+					String::new()
+				} else {
+					format!("{} / {}", cur + 1, images.len())
+				}),
+				text_input("Go to page...", page_input)
+					.on_input(Message::PageInputChanged)
+					.on_submit(Message::PageInputSubmitted)
+					.width(120),
+				pick_list(
+					&FitMode::ALL[..],
+					Some(fit_mode),
+					Message::FitModeSelected
+				),
+				button("Set as default").on_press(Message::SetDefaultFitMode),
+				pick_list(
+					&Rotation::ALL[..],
+					Some(rotation),
+					Message::PageRotationSelected
+				),
+				button("Filmstrip").on_press(Message::ToggleFilmstrip),
+				button("Overview").on_press(Message::ToggleOverview),
+				button("Adjust").on_press(Message::ToggleAdjustments),
+				button(if slideshow.is_some() {
+					"Stop slideshow"
+				} else {
+					"Slideshow"
+				})
+				.on_press(Message::ToggleSlideshow),
+				button("Fullscreen").on_press(Message::ToggleFullscreen),
+			]
+			.spacing(20)
+			.align_items(Alignment::Center),
+		);
+
+		if self.is_reading_fullscreen() {
+			let page = mouse_area(
+				container(page)
+					.width(Length::Fill)
+					.height(Length::Fill)
+					.center_x()
+					.center_y(),
+			)
+			.on_press(Message::PageClicked);
+			let mut col = column![page].width(Length::Fill);
+			if self.controls_shown_at.is_some() {
+				col = col.push(container(controls).padding(20));
+			} else if let Some(countdown) = countdown {
+				col = col.push(
+					container(text(countdown))
+						.width(Length::Fill)
+						.align_x(Horizontal::Right)
+						.padding(10),
+				);
+			}
+			return col;
+		}
+
 		column![
 			row![
 				button(
@@ -527,15 +3737,8 @@ impl<'a> App {
 				)
 				.style(theme::Button::Text)
 				.width(Length::Fill)
-				.on_press(Message::AdvancePage(false)),
-				img.map(|img| image(img.clone()))
-					.unwrap_or_else(|| {
-						image(format!(
-							"{}/images/waiting.png",
-							env!("CARGO_MANIFEST_DIR")
-						))
-					})
-					.content_fit(ContentFit::ScaleDown),
+				.on_press(Message::ZoneClicked(MouseInput::LeftZone)),
+				page,
 				button(
 					container(
 						svg(svg::Handle::from_path(format!(
@@ -557,16 +3760,171 @@ impl<'a> App {
 				)
 				.style(theme::Button::Text)
 				.width(Length::Fill)
-				.on_press(Message::AdvancePage(true)),
+				.on_press(Message::ZoneClicked(MouseInput::RightZone)),
 			]
 			.height(Length::Fill),
-			button("Back").on_press(Message::GoBack)
+			controls
 		]
 		.spacing(20)
 		.padding(20)
 		.width(Length::Fill)
 	}
 
+	fn adjustments_view(&self, book: &BookRef) -> Element<'a, Message> {
+		let own = book.read().unwrap().get_adjustments();
+		let adjustments = own.unwrap_or(self.library.get_adjustments());
+		let label_size = 100;
+
+		column![
+			row![
+				text("Brightness").width(label_size),
+				slider(-100..=100, adjustments.brightness, move |brightness| {
+					Message::AdjustmentsChanged(ImageAdjustments {
+						brightness,
+						..adjustments
+					})
+				}),
+				text("Contrast").width(label_size),
+				slider(-100.0..=100.0, adjustments.contrast, move |contrast| {
+					Message::AdjustmentsChanged(ImageAdjustments {
+						contrast,
+						..adjustments
+					})
+				}),
+			]
+			.spacing(20)
+			.align_items(Alignment::Center),
+			row![
+				text("Gamma").width(label_size),
+				slider(0.2..=3.0, adjustments.gamma, move |gamma| {
+					Message::AdjustmentsChanged(ImageAdjustments {
+						gamma,
+						..adjustments
+					})
+				})
+				.step(0.05),
+				text("Sharpen").width(label_size),
+				slider(0.0..=5.0, adjustments.sharpen, move |sharpen| {
+					Message::AdjustmentsChanged(ImageAdjustments {
+						sharpen,
+						..adjustments
+					})
+				})
+				.step(0.1),
+			]
+			.spacing(20)
+			.align_items(Alignment::Center),
+			row![
+				checkbox(
+					"Grayscale",
+					adjustments.grayscale,
+					move |grayscale| {
+						Message::AdjustmentsChanged(ImageAdjustments {
+							grayscale,
+							..adjustments
+						})
+					}
+				),
+				checkbox("Invert", adjustments.invert, move |invert| {
+					Message::AdjustmentsChanged(ImageAdjustments {
+						invert,
+						..adjustments
+					})
+				}),
+				checkbox(
+					"Only for this book",
+					own.is_some(),
+					Message::BookAdjustmentsToggled
+				),
+				horizontal_space(Length::Fill),
+				button("Reset").on_press(Message::ResetAdjustments),
+			]
+			.spacing(20)
+			.align_items(Alignment::Center),
+		]
+		.spacing(10)
+		.into()
+	}
+
+	fn filmstrip_view(
+		cur: usize,
+		thumbnails: &'a [Option<image::Handle>],
+	) -> Element<'a, Message> {
+		let mut strip = row![].spacing(10).padding([0, 0, 10, 0]);
+		for (index, thumbnail) in thumbnails.iter().enumerate() {
+			strip = strip.push(Self::thumbnail_button(
+				index,
+				index == cur,
+				thumbnail.as_ref(),
+			));
+		}
+		scrollable(strip)
+			.direction(scrollable::Direction::Horizontal(
+				scrollable::Properties::default(),
+			))
+			.on_scroll(|viewport| {
+				Message::ThumbnailsScrolled(viewport.relative_offset().x)
+			})
+			.into()
+	}
+
+	fn overview_view(
+		&self,
+		cur: usize,
+		thumbnails: &'a [Option<image::Handle>],
+	) -> Column<'a, Message> {
+		let mut col = column![].spacing(20).padding([0, 20, 0, 0]);
+		let chunk_size =
+			(self.win_width / OVERVIEW_THUMBNAIL_WIDTH as u32).max(1) as usize;
+		for (chunk_index, chunk) in thumbnails.chunks(chunk_size).enumerate() {
+			let mut row: Row<'a, Message> = row!().spacing(20);
+			for (offset, thumbnail) in chunk.iter().enumerate() {
+				let index = chunk_index * chunk_size + offset;
+				row = row.push(
+					column![
+						Self::thumbnail_button(
+							index,
+							index == cur,
+							thumbnail.as_ref()
+						),
+						text(index + 1).size(14)
+					]
+					.align_items(Alignment::Center)
+					.width(Length::Fill),
+				);
+			}
+			for _ in chunk.len()..chunk_size {
+				row = row.push(horizontal_space(Length::Fill));
+			}
+			col = col.push(row);
+		}
+
+		Self::container("Pages")
+			.push(scrollable(col).height(Length::Fill).on_scroll(|viewport| {
+				Message::ThumbnailsScrolled(viewport.relative_offset().y)
+			}))
+			.push(button("Back").on_press(Message::GoBack))
+	}
+
+	fn thumbnail_button(
+		index: usize,
+		is_current: bool,
+		thumbnail: Option<&image::Handle>,
+	) -> Element<'a, Message> {
+		let img = thumbnail.map(|t| image(t.clone())).unwrap_or_else(|| {
+			image(format!("{}/images/waiting.png", env!("CARGO_MANIFEST_DIR")))
+		});
+		button(img.height(100))
+			.padding(4)
+			.style(if is_current {
+				theme::Button::Primary
+			} else {
+				theme::Button::Text
+			})
+			.on_press(Message::GoToPage(index))
+			.into()
+	}
+
 	fn get_image_for_book(
 		&self,
 		book: &BookRef,
@@ -583,3 +3941,71 @@ impl<'a> App {
 			})
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use iced_runtime::command::Action as CommandAction;
+	use std::fs;
+	use tokio::runtime::Runtime;
+
+	/// Handles the message and whatever it leads to, running the futures
+	/// of the commands but none of the window actions.
+	fn run(app: &mut App, runtime: &Runtime, message: Message) {
+		let mut messages = vec![message];
+		while let Some(message) = messages.pop() {
+			for action in app.update(message).actions() {
+				if let CommandAction::Future(future) = action {
+					messages.push(runtime.block_on(future));
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn never_saves_over_a_newer_library() {
+		let dir =
+			env::temp_dir().join(format!("manga-reader-{}", Uuid::new_v4()));
+		fs::create_dir_all(&dir).unwrap();
+		let library_file = dir.join("library.json");
+		let json = r#"{"version": "99.0", "books": [], "future": true}"#;
+		fs::write(&library_file, json).unwrap();
+
+		let runtime = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap();
+		let (mut app, load) = App::new(Flags {
+			library_file: library_file.clone(),
+			keymap_file: dir.join("keymap.json"),
+		});
+		for action in load.actions() {
+			if let CommandAction::Future(future) = action {
+				let message = runtime.block_on(future);
+				run(&mut app, &runtime, message);
+			}
+		}
+		assert!(matches!(app.state, AppState::Errored(_)));
+
+		for action in Action::ALL {
+			run(&mut app, &runtime, action_message(action));
+		}
+		assert!(!app.library.is_dirty());
+		run(&mut app, &runtime, Message::SaveLibrary);
+		run(
+			&mut app,
+			&runtime,
+			Message::AutosaveTimerTick(Instant::now() + AUTOSAVE_DEBOUNCE),
+		);
+		run(&mut app, &runtime, Message::CloseRequested);
+
+		// Neither saved nor backed up, only the key bindings are written.
+		let mut files = fs::read_dir(&dir)
+			.unwrap()
+			.map(|entry| entry.unwrap().file_name())
+			.collect::<Vec<_>>();
+		files.sort();
+		assert_eq!(files, ["keymap.json", "library.json"]);
+		assert_eq!(fs::read_to_string(&library_file).unwrap(), json);
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,188 @@
+use crate::filename::parse_filename;
+use crate::library::{book_name, LoadError};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// The version of the library file written by this version of the app.
+pub const CURRENT_VERSION: &str = "1.1";
+
+/// An upgrade of the library file from one version to the next.
+struct Migration {
+	from: &'static str,
+	to: &'static str,
+	upgrade: fn(&mut Value),
+}
+
+/// Every upgrade, from the oldest version. Each one has to start from the
+/// version the previous one ends at, and the last one has to end at
+/// `CURRENT_VERSION`.
+const MIGRATIONS: &[Migration] = &[Migration {
+	from: "1.0",
+	to: "1.1",
+	upgrade: infer_file_info,
+}];
+
+/// Upgrades the JSON of a library file to the current version, one step at a
+/// time, refusing versions newer than the app.
+pub fn migrate(json: &mut Value) -> Result<(), LoadError> {
+	let Some(library) = json.as_object_mut() else {
+		return Err(LoadError::Damaged(
+			"The library file isn't a JSON object".to_owned(),
+		));
+	};
+	// Files from before the version was written are the first version.
+	let mut version = match library.get("version") {
+		None => MIGRATIONS[0].from.to_owned(),
+		Some(Value::String(version)) => version.clone(),
+		Some(version) => {
+			return Err(LoadError::Damaged(format!(
+				"Invalid library version {version}"
+			)));
+		}
+	};
+
+	while version != CURRENT_VERSION {
+		if is_newer(&version) {
+			return Err(LoadError::TooNew(format!(
+				"The library was saved by a newer version of the app \
+				 (library version {version}), please update the app"
+			)));
+		}
+		let Some(migration) = MIGRATIONS.iter().find(|m| m.from == version)
+		else {
+			return Err(LoadError::Damaged(format!(
+				"Unknown library version {version}"
+			)));
+		};
+		(migration.upgrade)(json);
+		version = migration.to.to_owned();
+	}
+	if let Some(library) = json.as_object_mut() {
+		library.insert("version".to_owned(), Value::String(version));
+	}
+	Ok(())
+}
+
+/// Compares `major.minor` versions, treating ones that can't be parsed as
+/// older so that they are reported as unknown.
+fn is_newer(version: &str) -> bool {
+	let parse = |version: &str| {
+		let (major, minor) = version.split_once('.')?;
+		Some((major.parse::<u32>().ok()?, minor.parse::<u32>().ok()?))
+	};
+	match (parse(version), parse(CURRENT_VERSION)) {
+		(Some(version), Some(current)) => version > current,
+		_ => false,
+	}
+}
+
+/// 1.0 to 1.1: fills in the series, volume, chapter, year and group of the
+/// books imported before they were inferred from file names.
+fn infer_file_info(json: &mut Value) {
+	let Some(books) = json.get_mut("books").and_then(Value::as_array_mut)
+	else {
+		return;
+	};
+	for book in books.iter_mut().filter_map(Value::as_object_mut) {
+		let Some(path) = book.get("path").and_then(Value::as_str) else {
+			continue;
+		};
+		let Some(name) = book_name(Path::new(path)) else {
+			continue;
+		};
+		let info = parse_filename(&name.to_string_lossy());
+		insert_missing(book, "series", info.series.map(Value::from));
+		insert_missing(book, "volume", info.volume.map(Value::from));
+		insert_missing(book, "chapter", info.chapter.map(Value::from));
+		insert_missing(book, "year", info.year.map(Value::from));
+		insert_missing(book, "scan_group", info.group.map(Value::from));
+	}
+}
+
+/// Sets the field, unless it is already set.
+fn insert_missing(
+	object: &mut Map<String, Value>,
+	key: &str,
+	value: Option<Value>,
+) {
+	let Some(value) = value else {
+		return;
+	};
+	if object.get(key).is_none_or(Value::is_null) {
+		object.insert(key.to_owned(), value);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::library::Library;
+
+	fn fixture(name: &str) -> Value {
+		let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+			.join("tests/fixtures")
+			.join(name);
+		let b = std::fs::read(&path).unwrap();
+		serde_json::from_slice(&b).unwrap()
+	}
+
+	#[test]
+	fn migrations_form_a_chain_to_the_current_version() {
+		for pair in MIGRATIONS.windows(2) {
+			assert_eq!(pair[0].to, pair[1].from);
+		}
+		assert_eq!(MIGRATIONS.last().unwrap().to, CURRENT_VERSION);
+	}
+
+	#[test]
+	fn upgrades_1_0_to_1_1() {
+		let mut json = fixture("library-1.0.json");
+		migrate(&mut json).unwrap();
+		assert_eq!(json, fixture("library-1.1.json"));
+	}
+
+	#[test]
+	fn leaves_current_version_alone() {
+		let mut json = fixture("library-1.1.json");
+		migrate(&mut json).unwrap();
+		assert_eq!(json, fixture("library-1.1.json"));
+	}
+
+	#[test]
+	fn treats_missing_version_as_first() {
+		let mut json = fixture("library-1.0.json");
+		json.as_object_mut().unwrap().remove("version");
+		migrate(&mut json).unwrap();
+		assert_eq!(json, fixture("library-1.1.json"));
+	}
+
+	#[test]
+	fn migrated_files_load() {
+		let mut json = fixture("library-1.0.json");
+		migrate(&mut json).unwrap();
+		let library: Library = serde_json::from_value(json).unwrap();
+		let book = &library.get_books()[0];
+		let book = book.read().unwrap();
+		assert_eq!(book.get_series(), "One Piece");
+		assert_eq!(book.get_volume(), Some(12));
+	}
+
+	#[test]
+	fn refuses_newer_versions() {
+		let mut json = fixture("library-1.1.json");
+		json["version"] = Value::from("2.0");
+		let err = migrate(&mut json).unwrap_err();
+		assert!(matches!(err, LoadError::TooNew(_)), "{err}");
+		assert!(err.to_string().contains("newer version"), "{err}");
+	}
+
+	#[test]
+	fn refuses_unknown_versions() {
+		for version in [Value::from("0.9"), Value::from("beta"), Value::from(1)]
+		{
+			let mut json = fixture("library-1.0.json");
+			json["version"] = version;
+			assert!(matches!(migrate(&mut json), Err(LoadError::Damaged(_))));
+		}
+	}
+}
@@ -0,0 +1,441 @@
+use crate::dates::SECONDS_PER_DAY;
+use crate::library::{Book, ReadStatus};
+use std::fmt;
+
+/// A search over the books of the library, such as
+/// `unread tag:shonen added:<30d`.
+///
+/// A query is made of terms separated by spaces or commas, and a book has to
+/// match every term. A term starting with `-` matches the books the rest of
+/// it doesn't. The terms are:
+///
+/// - `unread`, `reading`, `read` or `status:<status>` for the read status
+/// - `favorite` for the favorite books
+/// - `tag:<tag>` for the books with the tag
+/// - `author:<text>` and `title:<text>` for the books whose author or title
+///   contains the text
+/// - `rating:<n>` for the books with the rating, which can be preceded by
+///   `<`, `<=`, `>` or `>=`
+/// - `added:<n>d` for the books added `n` whole days ago, with the same
+///   comparisons, so `added:<30d` matches the books added in the last 30 days
+/// - any other word for the books whose title contains it
+///
+/// Values with spaces can be put in double quotes, as in `tag:"slice of life"`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Query {
+	terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+	negated: bool,
+	condition: Condition,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Condition {
+	Status(ReadStatus),
+	Favorite,
+	/// Holds the lowercase tag.
+	Tag(String),
+	/// Holds lowercase text to find in the author.
+	Author(String),
+	/// Holds lowercase text to find in the title.
+	Title(String),
+	Rating(Comparison, u8),
+	/// Compares the number of days since the book was added.
+	Added(Comparison, u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+	Less,
+	LessOrEqual,
+	Equal,
+	GreaterOrEqual,
+	Greater,
+}
+
+impl Comparison {
+	/// Splits the comparison operator off the start of the value.
+	fn parse(value: &str) -> (Self, &str) {
+		[
+			("<=", Comparison::LessOrEqual),
+			(">=", Comparison::GreaterOrEqual),
+			("<", Comparison::Less),
+			(">", Comparison::Greater),
+			("=", Comparison::Equal),
+		]
+		.into_iter()
+		.find_map(|(op, comparison)| {
+			value.strip_prefix(op).map(|rest| (comparison, rest))
+		})
+		.unwrap_or((Comparison::Equal, value))
+	}
+
+	fn compare<T: Ord>(self, value: T, other: T) -> bool {
+		match self {
+			Comparison::Less => value < other,
+			Comparison::LessOrEqual => value <= other,
+			Comparison::Equal => value == other,
+			Comparison::GreaterOrEqual => value >= other,
+			Comparison::Greater => value > other,
+		}
+	}
+}
+
+/// Why a query couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+impl Query {
+	pub fn parse(query: &str) -> Result<Self, ParseError> {
+		let terms = tokenize(query)?
+			.iter()
+			.map(parse_term)
+			.collect::<Result<Vec<Term>, ParseError>>()?;
+		Ok(Self { terms })
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.terms.is_empty()
+	}
+
+	/// Whether the book matches every term, where `now` is the current time
+	/// in seconds since the Unix epoch.
+	pub fn matches(&self, book: &Book, now: u64) -> bool {
+		self.terms
+			.iter()
+			.all(|term| term.condition.matches(book, now) != term.negated)
+	}
+}
+
+impl Condition {
+	fn matches(&self, book: &Book, now: u64) -> bool {
+		match self {
+			Condition::Status(status) => book.get_status() == *status,
+			Condition::Favorite => book.is_favorite(),
+			Condition::Tag(tag) => {
+				book.get_tags().iter().any(|t| t.to_lowercase() == *tag)
+			}
+			Condition::Author(text) => {
+				book.get_author().to_lowercase().contains(text)
+			}
+			Condition::Title(text) => {
+				book.get_title().to_lowercase().contains(text)
+			}
+			Condition::Rating(comparison, rating) => {
+				comparison.compare(book.get_rating(), *rating)
+			}
+			Condition::Added(comparison, days) => {
+				book.get_added().is_some_and(|added| {
+					let age = now.saturating_sub(added) / SECONDS_PER_DAY;
+					comparison.compare(age, *days)
+				})
+			}
+		}
+	}
+}
+
+/// A term of the query, with the quotes taken out.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Token {
+	text: String,
+	/// Whether the term started with a `-` outside of quotes.
+	negated: bool,
+	/// Where the `:` after the field name is, which can't be one that was
+	/// quoted.
+	colon: Option<usize>,
+}
+
+impl Token {
+	/// A lone `-` is searched for as it is, rather than negating nothing.
+	fn finish(mut self) -> Option<Self> {
+		if self.text.is_empty() && self.negated {
+			self.text.push('-');
+			self.negated = false;
+		}
+		(!self.text.is_empty()).then_some(self)
+	}
+}
+
+/// Splits the query into its terms, taking the quotes out of quoted values.
+fn tokenize(query: &str) -> Result<Vec<Token>, ParseError> {
+	let mut tokens = Vec::new();
+	let mut token = Token::default();
+	let mut quoted = false;
+	for c in query.chars() {
+		match c {
+			'"' => quoted = !quoted,
+			c if !quoted && (c.is_whitespace() || c == ',') => {
+				tokens.extend(std::mem::take(&mut token).finish());
+			}
+			'-' if !quoted && token == Token::default() => {
+				token.negated = true;
+			}
+			':' if !quoted && token.colon.is_none() => {
+				token.colon = Some(token.text.len());
+				token.text.push(c);
+			}
+			c => token.text.push(c),
+		}
+	}
+	if quoted {
+		return Err(ParseError("Missing closing quote".to_owned()));
+	}
+	tokens.extend(token.finish());
+	Ok(tokens)
+}
+
+fn parse_term(token: &Token) -> Result<Term, ParseError> {
+	let text = token.text.as_str();
+	let condition = match token.colon {
+		Some(colon) => parse_field(&text[..colon], &text[colon + 1..])?,
+		None => match parse_status(text) {
+			Some(status) => Condition::Status(status),
+			None if text.eq_ignore_ascii_case("favorite") => {
+				Condition::Favorite
+			}
+			None => Condition::Title(text.to_lowercase()),
+		},
+	};
+	Ok(Term {
+		negated: token.negated,
+		condition,
+	})
+}
+
+fn parse_field(field: &str, value: &str) -> Result<Condition, ParseError> {
+	if value.is_empty() {
+		return Err(ParseError(format!("Missing value for `{field}`")));
+	}
+	let field = field.to_lowercase();
+	match field.as_str() {
+		"status" => parse_status(value)
+			.map(Condition::Status)
+			.ok_or_else(|| ParseError(format!("Unknown status `{value}`"))),
+		"tag" => Ok(Condition::Tag(value.to_lowercase())),
+		"author" => Ok(Condition::Author(value.to_lowercase())),
+		"title" => Ok(Condition::Title(value.to_lowercase())),
+		"rating" => {
+			let (comparison, number) = Comparison::parse(value);
+			number
+				.parse()
+				.map(|rating| Condition::Rating(comparison, rating))
+				.map_err(|_| ParseError(format!("Invalid rating `{value}`")))
+		}
+		"added" => {
+			let (comparison, number) = Comparison::parse(value);
+			let number = number.strip_suffix('d').unwrap_or(number);
+			number
+				.parse()
+				.map(|days| Condition::Added(comparison, days))
+				.map_err(|_| {
+					ParseError(format!("Invalid number of days `{value}`"))
+				})
+		}
+		_ => Err(ParseError(format!("Unknown field `{field}`"))),
+	}
+}
+
+fn parse_status(value: &str) -> Option<ReadStatus> {
+	ReadStatus::ALL
+		.into_iter()
+		.find(|status| status.to_string().eq_ignore_ascii_case(value))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::path::Path;
+
+	fn book() -> Book {
+		let mut book = Book::new(Path::new("/manga/One Piece v01.cbz"));
+		book.set_author("Eiichiro Oda".to_owned());
+		book.set_tags(vec!["Shonen".to_owned(), "Slice of life".to_owned()]);
+		book.set_rating(4);
+		book.set_status(ReadStatus::Reading);
+		book
+	}
+
+	/// Ten days after the book was added.
+	fn now(book: &Book) -> u64 {
+		book.get_added().unwrap() + 10 * SECONDS_PER_DAY
+	}
+
+	fn matches(query: &str) -> bool {
+		let book = book();
+		Query::parse(query).unwrap().matches(&book, now(&book))
+	}
+
+	fn token_texts(query: &str) -> Vec<String> {
+		tokenize(query)
+			.unwrap()
+			.into_iter()
+			.map(|token| token.text)
+			.collect()
+	}
+
+	#[test]
+	fn splits_terms_on_spaces_and_commas() {
+		assert_eq!(
+			token_texts("unread, tag:shonen  added:<30d"),
+			["unread", "tag:shonen", "added:<30d"]
+		);
+	}
+
+	#[test]
+	fn keeps_quoted_values_together() {
+		assert_eq!(
+			token_texts(r#"tag:"slice of life", one"#),
+			["tag:slice of life", "one"]
+		);
+	}
+
+	#[test]
+	fn ignores_quoted_colons() {
+		let book = Book::new(Path::new("/manga/Re:Zero v01.cbz"));
+		let now = now(&book);
+		for query in [r#""Re:Zero""#, r#"title:"re:zero""#, "title:Re:Zero"] {
+			assert!(Query::parse(query).unwrap().matches(&book, now));
+		}
+		assert!(!Query::parse(r#"-"Re:Zero""#).unwrap().matches(&book, now));
+	}
+
+	#[test]
+	fn negates_only_outside_quotes() {
+		let book = Book::new(Path::new("/manga/-Man v01.cbz"));
+		let now = now(&book);
+		assert!(Query::parse(r#""-man""#).unwrap().matches(&book, now));
+		assert!(!Query::parse(r#"-"-man""#).unwrap().matches(&book, now));
+		assert_eq!(
+			Query::parse(r#""-favorite" -"#).unwrap().terms,
+			[
+				Term {
+					negated: false,
+					condition: Condition::Title("-favorite".to_owned()),
+				},
+				Term {
+					negated: false,
+					condition: Condition::Title("-".to_owned()),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn rejects_unterminated_quotes() {
+		assert!(Query::parse(r#"tag:"slice of"#).is_err());
+	}
+
+	#[test]
+	fn rejects_unknown_fields_and_bad_values() {
+		assert!(Query::parse("pages:3").is_err());
+		assert!(Query::parse("rating:lots").is_err());
+		assert!(Query::parse("added:soon").is_err());
+		assert!(Query::parse("status:finished").is_err());
+		assert!(Query::parse("tag:").is_err());
+	}
+
+	#[test]
+	fn empty_query_matches_everything() {
+		let query = Query::parse(" , ").unwrap();
+		assert!(query.is_empty());
+		assert!(query.matches(&book(), 0));
+	}
+
+	#[test]
+	fn parses_terms() {
+		assert_eq!(
+			Query::parse("-Read rating:>=3 added:<30d").unwrap().terms,
+			[
+				Term {
+					negated: true,
+					condition: Condition::Status(ReadStatus::Read),
+				},
+				Term {
+					negated: false,
+					condition: Condition::Rating(Comparison::GreaterOrEqual, 3),
+				},
+				Term {
+					negated: false,
+					condition: Condition::Added(Comparison::Less, 30),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn matches_status_and_favorite() {
+		assert!(matches("reading"));
+		assert!(matches("status:READING"));
+		assert!(!matches("unread"));
+		assert!(!matches("favorite"));
+		assert!(matches("-favorite"));
+	}
+
+	#[test]
+	fn matches_text_fields_ignoring_case() {
+		assert!(matches("tag:shonen"));
+		assert!(matches(r#"tag:"slice of life""#));
+		assert!(!matches("tag:slice"));
+		assert!(matches("author:oda"));
+		assert!(matches("title:piece"));
+		assert!(matches("one piece"));
+		assert!(!matches("naruto"));
+	}
+
+	#[test]
+	fn matches_comparisons() {
+		assert!(matches("rating:4"));
+		assert!(matches("rating:>3"));
+		assert!(!matches("rating:<4"));
+		assert!(matches("rating:<=4"));
+		assert!(matches("added:<30d"));
+		assert!(!matches("added:<7d"));
+		assert!(matches("added:>7"));
+	}
+
+	#[test]
+	fn matches_books_added_that_many_days_ago() {
+		assert!(matches("added:10d"));
+		assert!(matches("added:=10"));
+		assert!(!matches("added:9d"));
+		assert!(!matches("added:11d"));
+
+		// Part of a day doesn't count.
+		let book = book();
+		let query = Query::parse("added:10d").unwrap();
+		assert!(query.matches(&book, now(&book) + SECONDS_PER_DAY - 1));
+		assert!(!query.matches(&book, now(&book) + SECONDS_PER_DAY));
+	}
+
+	#[test]
+	fn requires_every_term() {
+		assert!(matches("reading, tag:shonen, added:<30d"));
+		assert!(!matches("reading, tag:seinen, added:<30d"));
+	}
+
+	#[test]
+	fn books_without_date_never_match_added() {
+		// Books imported before the date was recorded don't have one.
+		let book: Book = serde_json::from_str(
+			r#"{
+				"id": "67e55044-10b1-426f-9247-bb680e5fe0c8",
+				"author": null,
+				"path": "/manga/old.cbz",
+				"tags": [],
+				"title": null
+			}"#,
+		)
+		.unwrap();
+		let query = Query::parse("added:>=0d").unwrap();
+		assert!(!query.matches(&book, u64::MAX));
+	}
+}
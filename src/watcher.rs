@@ -0,0 +1,54 @@
+use iced::{
+	futures::{channel::mpsc, SinkExt, StreamExt},
+	subscription, Subscription,
+};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::PathBuf;
+
+/// Watches the folders and their subfolders, producing an item whenever a
+/// file in them is created, changed or removed.
+///
+/// Changes tend to come in bursts, such as while a file is being copied, so
+/// it's up to the receiver to wait for them to settle.
+pub fn watch_folders(folders: Vec<PathBuf>) -> Subscription<()> {
+	subscription::channel(folders.clone(), 16, |mut output| async move {
+		let (sender, mut receiver) = mpsc::unbounded();
+		let watcher = notify::recommended_watcher(
+			move |res: notify::Result<notify::Event>| match res {
+				Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+					let _ = sender.unbounded_send(());
+				}
+				Ok(_) => {}
+				Err(err) => eprintln!("Unable to watch folders: {err}"),
+			},
+		);
+		// The watcher has to be kept alive for as long as the subscription.
+		let _watcher = match watcher {
+			Ok(mut watcher) => {
+				for folder in &folders {
+					if let Err(err) =
+						watcher.watch(folder, RecursiveMode::Recursive)
+					{
+						eprintln!(
+							"Unable to watch folder {}: {err}",
+							folder.display()
+						);
+					}
+				}
+				Some(watcher)
+			}
+			Err(err) => {
+				eprintln!("Unable to watch folders: {err}");
+				None
+			}
+		};
+		loop {
+			match receiver.next().await {
+				Some(()) => {
+					let _ = output.send(()).await;
+				}
+				None => iced::futures::future::pending().await,
+			}
+		}
+	})
+}